@@ -0,0 +1,188 @@
+//! Parsing of byte sizes like `512MB` or `1GiB`
+//!
+//! This module may be used with serde's `with` field attribute to read byte sizes into a
+//! `u64` count of bytes. Decimal units (`KB`, `MB`, `GB`, `TB`) are powers of 1000 and
+//! binary units (`KiB`, `MiB`, `GiB`, `TiB`) are powers of 1024, matched ignoring case.
+//! Bare numbers are read as bytes. Single letter units like `M` are rejected, since it is
+//! unclear whether they are decimal or binary.
+//!
+//! # Example
+//!
+//! ```
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Config {
+//!     #[serde(with = "envy::bytesize")]
+//!     max_upload: u64,
+//! }
+//!
+//! let config: Config =
+//!     envy::from_iter(vec![(String::from("MAX_UPLOAD"), String::from("512MB"))]).unwrap();
+//! assert_eq!(config.max_upload, 512_000_000);
+//! ```
+use serde::{de, Deserializer, Serializer};
+use std::fmt;
+
+const UNITS: &[(&str, u64)] = &[
+    ("b", 1),
+    ("kb", 1_000),
+    ("mb", 1_000_000),
+    ("gb", 1_000_000_000),
+    ("tb", 1_000_000_000_000),
+    ("kib", 1 << 10),
+    ("mib", 1 << 20),
+    ("gib", 1 << 30),
+    ("tib", 1 << 40),
+];
+
+/// Parses a byte size such as `512MB`, `1.5GiB` or `1024`
+fn parse(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    if value.starts_with('-') {
+        return Err(String::from("byte sizes can not be negative"));
+    }
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let unit = unit.trim_start();
+    let multiplier = match UNITS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(unit))
+    {
+        Some((_, multiplier)) => *multiplier,
+        None if unit.is_empty() => 1,
+        None if ["k", "m", "g", "t"]
+            .iter()
+            .any(|u| u.eq_ignore_ascii_case(unit)) =>
+        {
+            return Err(format!(
+                "ambiguous byte size unit '{0}', use '{0}B' or '{0}iB'",
+                unit.to_uppercase()
+            ))
+        }
+        None => return Err(format!("unknown byte size unit '{}'", unit)),
+    };
+    if let Ok(amount) = amount.parse::<u64>() {
+        return amount
+            .checked_mul(multiplier)
+            .ok_or_else(|| String::from("byte size is too large"));
+    }
+    match amount.parse::<f64>() {
+        Ok(amount) if amount * (multiplier as f64) < u64::MAX as f64 => {
+            Ok((amount * multiplier as f64) as u64)
+        }
+        Ok(_) => Err(String::from("byte size is too large")),
+        Err(_) => Err(String::from("invalid byte size")),
+    }
+}
+
+struct ByteSizeVisitor;
+
+impl<'de> de::Visitor<'de> for ByteSizeVisitor {
+    type Value = u64;
+
+    fn expecting(
+        &self,
+        formatter: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        formatter.write_str("a byte size like 512MB")
+    }
+
+    fn visit_u64<E>(
+        self,
+        value: u64,
+    ) -> Result<u64, E> {
+        Ok(value)
+    }
+
+    fn visit_i64<E>(
+        self,
+        value: i64,
+    ) -> Result<u64, E>
+    where
+        E: de::Error,
+    {
+        u64::try_from(value).map_err(|_| E::custom("byte sizes can not be negative"))
+    }
+
+    fn visit_str<E>(
+        self,
+        value: &str,
+    ) -> Result<u64, E>
+    where
+        E: de::Error,
+    {
+        parse(value).map_err(E::custom)
+    }
+}
+
+/// Deserializes a count of bytes from a byte size
+pub fn deserialize<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(ByteSizeVisitor)
+}
+
+/// Serializes a count of bytes as is
+pub fn serialize<S>(
+    value: &u64,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_u64(*value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, ErrorCode};
+    use serde::Deserialize;
+
+    #[test]
+    fn parses_byte_sizes() {
+        assert_eq!(parse("1024"), Ok(1024));
+        assert_eq!(parse("512MB"), Ok(512_000_000));
+        assert_eq!(parse("1GiB"), Ok(1 << 30));
+        assert_eq!(parse("1.5 kib"), Ok(1536));
+        assert_eq!(
+            parse("-1MB"),
+            Err(String::from("byte sizes can not be negative"))
+        );
+        assert_eq!(
+            parse("2m"),
+            Err(String::from(
+                "ambiguous byte size unit 'M', use 'MB' or 'MiB'"
+            ))
+        );
+        assert_eq!(
+            parse("2 parsecs"),
+            Err(String::from("unknown byte size unit 'parsecs'"))
+        );
+        assert_eq!(parse("MB"), Err(String::from("invalid byte size")));
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Limits {
+        #[serde(with = "super")]
+        buffer: u64,
+    }
+
+    #[test]
+    fn names_var_in_errors() {
+        let data = vec![(String::from("BUFFER"), String::from("1G"))];
+        assert_eq!(
+            crate::from_iter::<_, Limits>(data),
+            Err(Error::InvalidValue {
+                var: String::from("BUFFER"),
+                value: String::from("1G"),
+                code: ErrorCode::Invalid,
+                message: String::from("ambiguous byte size unit 'G', use 'GB' or 'GiB'")
+            })
+        );
+    }
+}
@@ -0,0 +1,566 @@
+//! Buffered values, used to fill missing fields from a type's `Default`
+use crate::{secret, Error, Result};
+use serde::{
+    de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer},
+        IntoDeserializer,
+    },
+    ser::{self, Serialize},
+};
+
+/// A value captured from its `Serialize` impl, which deserializes back into the same value
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Content {
+    Bool(bool),
+    I64(i64),
+    I128(i128),
+    U64(u64),
+    U128(u128),
+    F64(f64),
+    Char(char),
+    String(String),
+    Bytes(Vec<u8>),
+    None,
+    Some(Box<Content>),
+    Unit,
+    Newtype(Box<Content>),
+    Seq(Vec<Content>),
+    Map(Vec<(Content, Content)>),
+    Variant(&'static str, Box<Content>),
+}
+
+impl Content {
+    /// Captures a value, including the values of any [Secret](../struct.Secret.html) it holds
+    pub(crate) fn capture<T>(value: &T) -> Result<Content>
+    where
+        T: ?Sized + Serialize,
+    {
+        secret::exposed(|| value.serialize(Capture))
+    }
+
+    /// The entries of a struct or map, keyed by name
+    pub(crate) fn fields(&self) -> impl Iterator<Item = (&str, &Content)> {
+        let entries = match self {
+            Content::Map(entries) => entries.as_slice(),
+            _ => &[],
+        };
+        entries.iter().filter_map(|(key, value)| match key {
+            Content::String(key) => Some((key.as_str(), value)),
+            _ => None,
+        })
+    }
+}
+
+/// Serializes a value into its `Content`
+struct Capture;
+
+/// Collects the elements of a sequence, tuple or tuple variant
+pub(crate) struct CaptureSeq {
+    variant: Option<&'static str>,
+    elements: Vec<Content>,
+}
+
+/// Collects the entries of a map, struct or struct variant
+pub(crate) struct CaptureMap {
+    variant: Option<&'static str>,
+    key: Option<Content>,
+    entries: Vec<(Content, Content)>,
+}
+
+/// Wraps the content of an enum's data in its variant, if any
+fn variant(
+    variant: Option<&'static str>,
+    content: Content,
+) -> Content {
+    match variant {
+        Some(variant) => Content::Variant(variant, Box::new(content)),
+        None => content,
+    }
+}
+
+macro_rules! capture_as {
+    ($($ty:ty => $method:ident => $variant:ident,)*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Content> {
+                Ok(Content::$variant(v.into()))
+            }
+        )*
+    }
+}
+
+impl ser::Serializer for Capture {
+    type Ok = Content;
+    type Error = Error;
+    type SerializeSeq = CaptureSeq;
+    type SerializeTuple = CaptureSeq;
+    type SerializeTupleStruct = CaptureSeq;
+    type SerializeTupleVariant = CaptureSeq;
+    type SerializeMap = CaptureMap;
+    type SerializeStruct = CaptureMap;
+    type SerializeStructVariant = CaptureMap;
+
+    capture_as! {
+        bool => serialize_bool => Bool,
+        i8 => serialize_i8 => I64,
+        i16 => serialize_i16 => I64,
+        i32 => serialize_i32 => I64,
+        i64 => serialize_i64 => I64,
+        i128 => serialize_i128 => I128,
+        u8 => serialize_u8 => U64,
+        u16 => serialize_u16 => U64,
+        u32 => serialize_u32 => U64,
+        u64 => serialize_u64 => U64,
+        u128 => serialize_u128 => U128,
+        f32 => serialize_f32 => F64,
+        f64 => serialize_f64 => F64,
+        char => serialize_char => Char,
+        &str => serialize_str => String,
+        &[u8] => serialize_bytes => Bytes,
+    }
+
+    fn serialize_none(self) -> Result<Content> {
+        Ok(Content::None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(
+        self,
+        value: &T,
+    ) -> Result<Content> {
+        Ok(Content::Some(Box::new(value.serialize(self)?)))
+    }
+
+    fn serialize_unit(self) -> Result<Content> {
+        Ok(Content::Unit)
+    }
+
+    fn serialize_unit_struct(
+        self,
+        _name: &'static str,
+    ) -> Result<Content> {
+        Ok(Content::Unit)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Content> {
+        Ok(Content::Variant(variant, Box::new(Content::Unit)))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Content> {
+        Ok(Content::Newtype(Box::new(value.serialize(self)?)))
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Content> {
+        Ok(Content::Variant(
+            variant,
+            Box::new(Content::Newtype(Box::new(value.serialize(self)?))),
+        ))
+    }
+
+    fn serialize_seq(
+        self,
+        len: Option<usize>,
+    ) -> Result<CaptureSeq> {
+        Ok(CaptureSeq {
+            variant: None,
+            elements: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(
+        self,
+        len: usize,
+    ) -> Result<CaptureSeq> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<CaptureSeq> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<CaptureSeq> {
+        Ok(CaptureSeq {
+            variant: Some(variant),
+            elements: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(
+        self,
+        len: Option<usize>,
+    ) -> Result<CaptureMap> {
+        Ok(CaptureMap {
+            variant: None,
+            key: None,
+            entries: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<CaptureMap> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<CaptureMap> {
+        Ok(CaptureMap {
+            variant: Some(variant),
+            key: None,
+            entries: Vec::with_capacity(len),
+        })
+    }
+}
+
+impl ser::SerializeSeq for CaptureSeq {
+    type Ok = Content;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        self.elements.push(value.serialize(Capture)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Content> {
+        Ok(variant(self.variant, Content::Seq(self.elements)))
+    }
+}
+
+impl ser::SerializeTuple for CaptureSeq {
+    type Ok = Content;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Content> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for CaptureSeq {
+    type Ok = Content;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Content> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for CaptureSeq {
+    type Ok = Content;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Content> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeMap for CaptureMap {
+    type Ok = Content;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(
+        &mut self,
+        key: &T,
+    ) -> Result<()> {
+        self.key = Some(key.serialize(Capture)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        let key = self.key.take().unwrap_or(Content::Unit);
+        self.entries.push((key, value.serialize(Capture)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Content> {
+        Ok(variant(self.variant, Content::Map(self.entries)))
+    }
+}
+
+impl ser::SerializeStruct for CaptureMap {
+    type Ok = Content;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.entries
+            .push((Content::String(key.to_owned()), value.serialize(Capture)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Content> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for CaptureMap {
+    type Ok = Content;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Content> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Content {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Content {
+    type Error = Error;
+
+    fn deserialize_any<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Content::Bool(v) => visitor.visit_bool(v),
+            Content::I64(v) => visitor.visit_i64(v),
+            Content::I128(v) => visitor.visit_i128(v),
+            Content::U64(v) => visitor.visit_u64(v),
+            Content::U128(v) => visitor.visit_u128(v),
+            Content::F64(v) => visitor.visit_f64(v),
+            Content::Char(v) => visitor.visit_char(v),
+            Content::String(v) => visitor.visit_string(v),
+            Content::Bytes(v) => visitor.visit_byte_buf(v),
+            Content::None => visitor.visit_none(),
+            Content::Some(v) => visitor.visit_some(*v),
+            Content::Unit => visitor.visit_unit(),
+            Content::Newtype(v) => visitor.visit_newtype_struct(*v),
+            Content::Seq(v) => visitor.visit_seq(SeqDeserializer::new(v.into_iter())),
+            Content::Map(v) => visitor.visit_map(MapDeserializer::new(v.into_iter())),
+            Content::Variant(..) => visitor.visit_enum(self),
+        }
+    }
+
+    fn deserialize_option<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Content::None | Content::Unit => visitor.visit_none(),
+            Content::Some(v) => visitor.visit_some(*v),
+            v => visitor.visit_some(v),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Content::Newtype(v) => visitor.visit_newtype_struct(*v),
+            v => visitor.visit_newtype_struct(v),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Content::String(v) => visitor.visit_enum(v.into_deserializer()),
+            v => visitor.visit_enum(v),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for Content {
+    type Error = Error;
+    type Variant = Content;
+
+    fn variant_seed<S>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Content)>
+    where
+        S: de::DeserializeSeed<'de>,
+    {
+        let (variant, data) = match self {
+            Content::Variant(variant, data) => (variant, *data),
+            other => {
+                return Err(de::Error::invalid_type(
+                    de::Unexpected::Other(&format!("{:?}", other)),
+                    &"an enum variant",
+                ))
+            }
+        };
+        let variant =
+            seed.deserialize(de::IntoDeserializer::<Error>::into_deserializer(variant))?;
+        Ok((variant, data))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Content {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S>(
+        self,
+        seed: S,
+    ) -> Result<S::Value>
+    where
+        S: de::DeserializeSeed<'de>,
+    {
+        match self {
+            Content::Newtype(v) => seed.deserialize(*v),
+            v => seed.deserialize(v),
+        }
+    }
+
+    fn tuple_variant<V>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Mode {
+        Off,
+        Fixed(u8),
+        Range { min: u8, max: u8 },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Captured {
+        name: String,
+        labels: BTreeMap<String, String>,
+        modes: Vec<Mode>,
+        limit: Option<(u32, char)>,
+        token: crate::Secret<String>,
+    }
+
+    #[test]
+    fn round_trips_captured_values() {
+        let captured = Captured {
+            name: String::from("a, b"),
+            labels: BTreeMap::from([(String::from("team"), String::from("x:y"))]),
+            modes: vec![Mode::Off, Mode::Fixed(1), Mode::Range { min: 1, max: 2 }],
+            limit: Some((3, 'c')),
+            token: crate::Secret::new(String::from("hunter2")),
+        };
+        let content = Content::capture(&captured).unwrap();
+        let restored = Captured::deserialize(content).unwrap();
+        assert_eq!(restored.token.expose(), "hunter2");
+        assert_eq!(restored, captured);
+    }
+}
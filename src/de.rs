@@ -0,0 +1,636 @@
+//! Helpers for fields whose values need special treatment
+//!
+//! These are used with serde's `deserialize_with` or `with` field attributes.
+use serde::{Deserialize, Deserializer};
+
+/// Deserializes each character of a value as an element of a `Vec<char>`
+///
+/// Values are not split on commas, since the characters are the data.
+///
+/// # Example
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     #[serde(deserialize_with = "envy::de::char_list")]
+///     delimiters: Vec<char>,
+/// }
+///
+/// let config: Config =
+///     envy::from_iter(vec![(String::from("DELIMITERS"), String::from(",;|"))]).unwrap();
+/// assert_eq!(config.delimiters, vec![',', ';', '|']);
+/// ```
+pub fn char_list<'de, D>(deserializer: D) -> Result<Vec<char>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(String::deserialize(deserializer)?.chars().collect())
+}
+
+/// Deserializes and serializes [bitflags](https://docs.rs/bitflags) types from comma separated
+/// flag names
+///
+/// Each named flag is combined into a single value, so `PERMS=READ,WRITE` sets both the
+/// `READ` and `WRITE` flags. Names are matched exactly and unknown names are rejected.
+///
+/// # Example
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// bitflags::bitflags! {
+///     #[derive(Debug, PartialEq)]
+///     struct Perms: u8 {
+///         const READ = 1;
+///         const WRITE = 2;
+///     }
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     #[serde(with = "envy::de::bitflags")]
+///     perms: Perms,
+/// }
+///
+/// let config: Config =
+///     envy::from_iter(vec![(String::from("PERMS"), String::from("READ,WRITE"))]).unwrap();
+/// assert_eq!(config.perms, Perms::READ | Perms::WRITE);
+/// ```
+#[cfg(feature = "bitflags")]
+pub mod bitflags {
+    use ::bitflags::Flags;
+    use serde::{de, Deserializer, Serializer};
+    use std::{fmt, marker::PhantomData};
+
+    struct FlagsVisitor<T>(PhantomData<T>);
+
+    impl<'de, T> de::Visitor<'de> for FlagsVisitor<T>
+    where
+        T: Flags,
+    {
+        type Value = T;
+
+        fn expecting(
+            &self,
+            formatter: &mut fmt::Formatter,
+        ) -> fmt::Result {
+            formatter.write_str("a comma separated list of flag names")
+        }
+
+        fn visit_str<E>(
+            self,
+            value: &str,
+        ) -> Result<T, E>
+        where
+            E: de::Error,
+        {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .try_fold(T::empty(), |flags, name| match T::from_name(name) {
+                    Some(flag) => Ok(flags.union(flag)),
+                    None => Err(E::custom(format_args!("unknown flag '{}'", name))),
+                })
+        }
+    }
+
+    /// Deserializes flags from comma separated flag names
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Flags,
+    {
+        deserializer.deserialize_str(FlagsVisitor(PhantomData))
+    }
+
+    /// Serializes flags into comma separated flag names
+    pub fn serialize<S, T>(
+        flags: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: Flags,
+    {
+        let names = flags.iter_names().map(|(name, _)| name).collect::<Vec<_>>();
+        serializer.serialize_str(&names.join(","))
+    }
+}
+
+/// Deserializes and serializes `f64` fields which accept percentages
+///
+/// A trailing `%` divides the value by 100, so `75%` yields `0.75`. Values without a `%`
+/// are parsed as is.
+///
+/// # Example
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     #[serde(with = "envy::de::percent")]
+///     load_factor: f64,
+/// }
+///
+/// let config: Config =
+///     envy::from_iter(vec![(String::from("LOAD_FACTOR"), String::from("75%"))]).unwrap();
+/// assert_eq!(config.load_factor, 0.75);
+/// ```
+pub mod percent {
+    use serde::{de, Deserializer, Serializer};
+    use std::fmt;
+
+    struct PercentVisitor;
+
+    impl<'de> de::Visitor<'de> for PercentVisitor {
+        type Value = f64;
+
+        fn expecting(
+            &self,
+            formatter: &mut fmt::Formatter,
+        ) -> fmt::Result {
+            formatter.write_str("a number or a percentage")
+        }
+
+        fn visit_f64<E>(
+            self,
+            value: f64,
+        ) -> Result<f64, E> {
+            Ok(value)
+        }
+
+        fn visit_i64<E>(
+            self,
+            value: i64,
+        ) -> Result<f64, E> {
+            Ok(value as f64)
+        }
+
+        fn visit_u64<E>(
+            self,
+            value: u64,
+        ) -> Result<f64, E> {
+            Ok(value as f64)
+        }
+
+        fn visit_str<E>(
+            self,
+            value: &str,
+        ) -> Result<f64, E>
+        where
+            E: de::Error,
+        {
+            let (number, scale) = match value.trim().strip_suffix('%') {
+                Some(number) => (number.trim_end(), 100.0),
+                None => (value.trim(), 1.0),
+            };
+            number
+                .parse::<f64>()
+                .map(|number| number / scale)
+                .map_err(E::custom)
+        }
+    }
+
+    /// Deserializes a number, or a percentage as a fraction
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<f64, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(PercentVisitor)
+    }
+
+    /// Serializes a number as is
+    pub fn serialize<S>(
+        value: &f64,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_f64(*value)
+    }
+}
+
+/// Deserializes and serializes `Vec<u8>` fields from base64 values
+///
+/// Values use the standard base64 alphabet, with or without `=` padding. See
+/// [base64_list](base64_list/index.html) for lists of base64 values.
+///
+/// # Example
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     #[serde(with = "envy::de::base64")]
+///     key: Vec<u8>,
+/// }
+///
+/// let config: Config =
+///     envy::from_iter(vec![(String::from("KEY"), String::from("aGk="))]).unwrap();
+/// assert_eq!(config.key, b"hi");
+/// ```
+pub mod base64 {
+    use serde::{de, Deserializer, Serializer};
+    use std::fmt;
+
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    pub(super) fn encode(bytes: &[u8]) -> String {
+        let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        for chunk in bytes.chunks(3) {
+            let n = chunk
+                .iter()
+                .enumerate()
+                .fold(0_u32, |n, (i, b)| n | u32::from(*b) << (16 - 8 * i));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    encoded.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+                } else {
+                    encoded.push('=');
+                }
+            }
+        }
+        encoded
+    }
+
+    fn decode(value: &str) -> Result<Vec<u8>, &'static str> {
+        let value = value.trim().trim_end_matches('=');
+        let mut bytes = Vec::with_capacity(value.len() * 3 / 4);
+        let mut n = 0_u32;
+        for (i, c) in value.bytes().enumerate() {
+            let digit = ALPHABET
+                .iter()
+                .position(|a| *a == c)
+                .ok_or("invalid base64 character")?;
+            n = n << 6 | digit as u32;
+            if i % 4 == 3 {
+                bytes.extend_from_slice(&n.to_be_bytes()[1..]);
+                n = 0;
+            }
+        }
+        match value.len() % 4 {
+            0 => {}
+            2 => bytes.push((n >> 4) as u8),
+            3 => bytes.extend_from_slice(&((n >> 2) as u16).to_be_bytes()),
+            _ => return Err("invalid base64 length"),
+        }
+        Ok(bytes)
+    }
+
+    pub(super) struct Base64Visitor;
+
+    impl<'de> de::Visitor<'de> for Base64Visitor {
+        type Value = Vec<u8>;
+
+        fn expecting(
+            &self,
+            formatter: &mut fmt::Formatter,
+        ) -> fmt::Result {
+            formatter.write_str("a base64 value")
+        }
+
+        fn visit_str<E>(
+            self,
+            value: &str,
+        ) -> Result<Vec<u8>, E>
+        where
+            E: de::Error,
+        {
+            decode(value).map_err(E::custom)
+        }
+    }
+
+    /// Deserializes bytes from a base64 value
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(Base64Visitor)
+    }
+
+    /// Serializes bytes as a padded base64 value
+    pub fn serialize<S>(
+        value: &[u8],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&encode(value))
+    }
+}
+
+/// Deserializes and serializes `Vec<Vec<u8>>` fields from lists of base64 values
+///
+/// Each element of a list like `KEYS=aGk=,dGhlcmU=` is decoded as a
+/// [base64](base64/index.html) value. Errors name the var and the index of the element
+/// which failed to decode.
+pub mod base64_list {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    struct Blob(Vec<u8>);
+
+    impl<'de> Deserialize<'de> for Blob {
+        fn deserialize<D>(deserializer: D) -> Result<Blob, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer
+                .deserialize_str(super::base64::Base64Visitor)
+                .map(Blob)
+        }
+    }
+
+    /// Deserializes a list of bytes from separated base64 values
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Vec<u8>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Vec::<Blob>::deserialize(deserializer)?
+            .into_iter()
+            .map(|Blob(bytes)| bytes)
+            .collect())
+    }
+
+    /// Serializes a list of bytes as padded base64 values
+    pub fn serialize<S>(
+        value: &[Vec<u8>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(value.iter().map(|bytes| super::base64::encode(bytes)))
+    }
+}
+
+/// Deserializes and serializes `time::Duration` fields from human friendly values
+///
+/// Values are read like [envy::duration](../duration/index.html) values and may be negated
+/// with a leading `-`, like `-30s`. Bare numbers are always read as seconds, regardless of
+/// [Config::duration_default_unit](../struct.Config.html#method.duration_default_unit).
+/// `std::time::Duration` fields are read with [envy::duration](../duration/index.html).
+#[cfg(feature = "time")]
+pub mod duration_time {
+    use crate::{duration, Unit};
+    use serde::{de, Deserializer, Serializer};
+    use std::fmt;
+    use time::Duration;
+
+    struct DurationVisitor;
+
+    impl<'de> de::Visitor<'de> for DurationVisitor {
+        type Value = Duration;
+
+        fn expecting(
+            &self,
+            formatter: &mut fmt::Formatter,
+        ) -> fmt::Result {
+            formatter.write_str("a duration like 30s")
+        }
+
+        fn visit_str<E>(
+            self,
+            value: &str,
+        ) -> Result<Duration, E>
+        where
+            E: de::Error,
+        {
+            let (negative, value) = match value.trim_start().strip_prefix('-') {
+                Some(value) => (true, value),
+                None => (false, value),
+            };
+            let duration = duration::parse(value, Unit::Secs)
+                .map_err(E::custom)
+                .and_then(|duration| Duration::try_from(duration).map_err(E::custom))?;
+            Ok(if negative { -duration } else { duration })
+        }
+    }
+
+    /// Deserializes a duration from a human friendly value
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(DurationVisitor)
+    }
+
+    /// Serializes a duration into a value accepted by [deserialize](fn.deserialize.html)
+    pub fn serialize<S>(
+        value: &Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if value.subsec_nanoseconds() == 0 {
+            serializer.collect_str(&format_args!("{}s", value.whole_seconds()))
+        } else {
+            serializer.collect_str(&format_args!("{}ns", value.whole_nanoseconds()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Tokenizer {
+        #[serde(deserialize_with = "super::char_list")]
+        delimiters: Vec<char>,
+        words: Vec<String>,
+    }
+
+    #[test]
+    fn deserializes_char_list() {
+        let data = vec![
+            (String::from("DELIMITERS"), String::from(",; |")),
+            (String::from("WORDS"), String::from("a,b")),
+        ];
+        assert_eq!(
+            crate::from_iter::<_, Tokenizer>(data),
+            Ok(Tokenizer {
+                delimiters: vec![',', ';', ' ', '|'],
+                words: vec![String::from("a"), String::from("b")]
+            })
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Tuning {
+        #[serde(with = "super::percent")]
+        load_factor: f64,
+    }
+
+    #[test]
+    fn deserializes_percentages() {
+        for (value, expected) in [("75%", 0.75), ("0.75", 0.75), ("12.5 %", 0.125)] {
+            let data = vec![(String::from("LOAD_FACTOR"), String::from(value))];
+            assert_eq!(
+                crate::from_iter::<_, Tuning>(data),
+                Ok(Tuning {
+                    load_factor: expected
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn fails_with_invalid_percentage() {
+        let data = vec![(String::from("LOAD_FACTOR"), String::from("abc%"))];
+        assert_eq!(
+            crate::from_iter::<_, Tuning>(data),
+            Err(crate::Error::InvalidValue {
+                var: String::from("LOAD_FACTOR"),
+                value: String::from("abc%"),
+                code: crate::ErrorCode::Invalid,
+                message: String::from("invalid float literal")
+            })
+        );
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    pub struct Keys {
+        #[serde(with = "super::base64_list")]
+        keys: Vec<Vec<u8>>,
+    }
+
+    #[test]
+    fn deserializes_base64_lists() {
+        let data = vec![(String::from("KEYS"), String::from("aGk=,dGhlcmU,"))];
+        assert_eq!(
+            crate::from_iter::<_, Keys>(data),
+            Ok(Keys {
+                keys: vec![b"hi".to_vec(), b"there".to_vec(), Vec::new()]
+            })
+        );
+        let keys = Keys {
+            keys: vec![b"hi".to_vec(), b"there".to_vec()],
+        };
+        assert_eq!(
+            crate::to_vec(&keys),
+            Ok(vec![(String::from("KEYS"), String::from("aGk=,dGhlcmU="))])
+        );
+        let data = vec![(String::from("KEYS"), String::from("aGk=,not*base64"))];
+        assert_eq!(
+            crate::from_iter::<_, Keys>(data),
+            Err(crate::Error::SeqElement {
+                var: String::from("KEYS"),
+                index: 1,
+                value: String::from("not*base64"),
+                code: crate::ErrorCode::Invalid,
+                message: String::from("invalid base64 character")
+            })
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct StdTimeout {
+        #[serde(with = "crate::duration")]
+        timeout: std::time::Duration,
+    }
+
+    #[test]
+    fn deserializes_std_durations() {
+        let data = vec![(String::from("TIMEOUT"), String::from("1.5m"))];
+        assert_eq!(
+            crate::from_iter::<_, StdTimeout>(data),
+            Ok(StdTimeout {
+                timeout: std::time::Duration::from_secs(90)
+            })
+        );
+        let data = vec![(String::from("TIMEOUT"), String::from("5 fortnights"))];
+        assert_eq!(
+            crate::from_iter::<_, StdTimeout>(data),
+            Err(crate::Error::InvalidValue {
+                var: String::from("TIMEOUT"),
+                value: String::from("5 fortnights"),
+                code: crate::ErrorCode::Invalid,
+                message: String::from("unknown duration unit 'fortnights'")
+            })
+        );
+    }
+
+    #[cfg(feature = "time")]
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct TimeOffset {
+        #[serde(with = "super::duration_time")]
+        offset: time::Duration,
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn deserializes_time_durations() {
+        let data = vec![(String::from("OFFSET"), String::from("-250ms"))];
+        assert_eq!(
+            crate::from_iter::<_, TimeOffset>(data),
+            Ok(TimeOffset {
+                offset: time::Duration::milliseconds(-250)
+            })
+        );
+        let data = vec![(String::from("OFFSET"), String::from("soon"))];
+        assert_eq!(
+            crate::from_iter::<_, TimeOffset>(data),
+            Err(crate::Error::InvalidValue {
+                var: String::from("OFFSET"),
+                value: String::from("soon"),
+                code: crate::ErrorCode::Invalid,
+                message: String::from("invalid duration")
+            })
+        );
+    }
+
+    #[cfg(feature = "bitflags")]
+    mod bitflags {
+        use crate::{Error, ErrorCode};
+        use serde::Deserialize;
+
+        ::bitflags::bitflags! {
+            #[derive(Debug, PartialEq)]
+            pub struct Perms: u8 {
+                const READ = 1;
+                const WRITE = 2;
+                const EXECUTE = 4;
+                const DELETE = 8;
+            }
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        pub struct Access {
+            #[serde(with = "crate::de::bitflags")]
+            perms: Perms,
+        }
+
+        #[test]
+        fn combines_flags() {
+            let data = vec![(String::from("PERMS"), String::from("READ, WRITE,EXECUTE"))];
+            assert_eq!(
+                crate::from_iter::<_, Access>(data),
+                Ok(Access {
+                    perms: Perms::READ | Perms::WRITE | Perms::EXECUTE
+                })
+            );
+        }
+
+        #[test]
+        fn fails_with_unknown_flag() {
+            let data = vec![(String::from("PERMS"), String::from("READ,FLY"))];
+            assert_eq!(
+                crate::from_iter::<_, Access>(data),
+                Err(Error::InvalidValue {
+                    var: String::from("PERMS"),
+                    value: String::from("READ,FLY"),
+                    code: ErrorCode::Invalid,
+                    message: String::from("unknown flag 'FLY'")
+                })
+            );
+        }
+    }
+}
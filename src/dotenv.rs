@@ -0,0 +1,121 @@
+//! Parsing of dotenv style files
+use crate::{Error, Result};
+use std::{fs, path::Path};
+
+/// Reads the `KEY=value` pairs of a dotenv style file
+pub(crate) fn read(path: &Path) -> Result<Vec<(String, String)>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| Error::Custom(format!("failed to read {}: {}", path.display(), e)))?;
+    parse(&contents).map_err(|line| {
+        Error::Custom(format!(
+            "invalid line {} in {}: expected KEY=value",
+            line,
+            path.display()
+        ))
+    })
+}
+
+/// Parses the `KEY=value` pairs of dotenv style contents
+///
+/// Blank lines and lines starting with `#` are ignored, a leading `export` is
+/// allowed and values may be wrapped in single or double quotes. On failure
+/// the number of the offending line is returned
+pub(crate) fn parse(contents: &str) -> std::result::Result<Vec<(String, String)>, usize> {
+    let mut pairs = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line
+            .strip_prefix("export")
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+            .unwrap_or(line);
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => (key.trim(), value.trim()),
+            _ => return Err(number + 1),
+        };
+        pairs.push((key.to_owned(), unquote(value)));
+    }
+    Ok(pairs)
+}
+
+fn unquote(value: &str) -> String {
+    if let Some(unquoted) = unquote_whole(value) {
+        return unquoted;
+    }
+    // quoted values may also be followed by a comment
+    if value.starts_with(['\'', '"']) {
+        let quoted = value
+            .match_indices(" #")
+            .find_map(|(index, _)| unquote_whole(value[..index].trim_end()));
+        if let Some(unquoted) = quoted {
+            return unquoted;
+        }
+    }
+    // unquoted values may be followed by a comment
+    match value.find(" #") {
+        Some(index) => value[..index].trim_end().to_owned(),
+        None => value.to_owned(),
+    }
+}
+
+/// Unquotes a value wrapped entirely in single or double quotes
+fn unquote_whole(value: &str) -> Option<String> {
+    if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        return Some(value[1..value.len() - 1].to_owned());
+    }
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        let mut unquoted = String::new();
+        let mut chars = value[1..value.len() - 1].chars();
+        while let Some(c) = chars.next() {
+            match (c, chars.clone().next()) {
+                ('\\', Some('n')) => {
+                    chars.next();
+                    unquoted.push('\n');
+                }
+                ('\\', Some(escaped @ ('"' | '\\'))) => {
+                    chars.next();
+                    unquoted.push(escaped);
+                }
+                _ => unquoted.push(c),
+            }
+        }
+        return Some(unquoted);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pairs() {
+        let contents = r#"
+# a comment
+FOO=bar
+export BAZ = "quoted \"value\"\nnext"
+SINGLE='it''s # literal'
+EMPTY=
+TRAILING=value # comment
+QUOTED="value # kept" # comment
+"#;
+        assert_eq!(
+            parse(contents),
+            Ok(vec![
+                (String::from("FOO"), String::from("bar")),
+                (String::from("BAZ"), String::from("quoted \"value\"\nnext")),
+                (String::from("SINGLE"), String::from("it''s # literal")),
+                (String::from("EMPTY"), String::new()),
+                (String::from("TRAILING"), String::from("value")),
+                (String::from("QUOTED"), String::from("value # kept")),
+            ])
+        );
+    }
+
+    #[test]
+    fn fails_with_invalid_line() {
+        assert_eq!(parse("FOO=bar\nnot a pair\n"), Err(2));
+    }
+}
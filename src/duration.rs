@@ -0,0 +1,268 @@
+//! Parsing of human friendly durations
+//!
+//! This module may be used with serde's `with` field attribute to read values like `30s`,
+//! `2m` or `250ms` into a `std::time::Duration`. Bare numbers are read in the unit set with
+//! [Config::duration_default_unit](../struct.Config.html#method.duration_default_unit),
+//! seconds by default. Plain `Duration` fields, without this attribute, are not read from
+//! human friendly values.
+//!
+//! # Example
+//!
+//! ```
+//! use serde::Deserialize;
+//! use std::time::Duration;
+//!
+//! #[derive(Deserialize)]
+//! struct Config {
+//!     #[serde(with = "envy::duration")]
+//!     cache_ttl: Duration,
+//! }
+//!
+//! let config: Config =
+//!     envy::from_iter(vec![(String::from("CACHE_TTL"), String::from("2m"))]).unwrap();
+//! assert_eq!(config.cache_ttl, Duration::from_secs(120));
+//! ```
+use serde::{de, Deserializer, Serializer};
+use std::{fmt, time::Duration};
+
+/// The name of the newtype struct durations are requested as, allowing envy to parse them
+/// with its configured default unit
+pub(crate) const MARKER: &str = "$envy::duration";
+
+/// The unit of a duration value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Unit {
+    /// Nanoseconds, with the suffix `ns`
+    Nanos,
+    /// Microseconds, with the suffix `us` or `µs`
+    Micros,
+    /// Milliseconds, with the suffix `ms`
+    Millis,
+    /// Seconds, with the suffix `s`, `sec` or `secs`
+    #[default]
+    Secs,
+    /// Minutes, with the suffix `m`, `min` or `mins`
+    Mins,
+    /// Hours, with the suffix `h`, `hr` or `hrs`
+    Hours,
+    /// Days, with the suffix `d`, `day` or `days`
+    Days,
+}
+
+impl Unit {
+    fn from_suffix(suffix: &str) -> Option<Unit> {
+        Some(match suffix {
+            "ns" => Unit::Nanos,
+            "us" | "µs" => Unit::Micros,
+            "ms" => Unit::Millis,
+            "s" | "sec" | "secs" => Unit::Secs,
+            "m" | "min" | "mins" => Unit::Mins,
+            "h" | "hr" | "hrs" => Unit::Hours,
+            "d" | "day" | "days" => Unit::Days,
+            _ => return None,
+        })
+    }
+
+    fn nanos(self) -> u128 {
+        match self {
+            Unit::Nanos => 1,
+            Unit::Micros => 1_000,
+            Unit::Millis => 1_000_000,
+            Unit::Secs => 1_000_000_000,
+            Unit::Mins => 60 * 1_000_000_000,
+            Unit::Hours => 60 * 60 * 1_000_000_000,
+            Unit::Days => 24 * 60 * 60 * 1_000_000_000,
+        }
+    }
+}
+
+/// Parses a duration such as `30s`, `1.5h`, `250ms` or `1h30m`
+///
+/// Components of a number and a unit are summed. A bare number, which must be the only
+/// component, is read in the `default` unit
+pub(crate) fn parse(
+    value: &str,
+    default: Unit,
+) -> Result<Duration, String> {
+    let mut rest = value.trim();
+    let mut total = Duration::ZERO;
+    loop {
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let (amount, tail) = rest.split_at(split);
+        if amount.is_empty() {
+            return Err(String::from("invalid duration"));
+        }
+        let tail = tail.trim_start();
+        let split = tail
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(tail.len());
+        let (suffix, tail) = tail.split_at(split);
+        let unit = match suffix {
+            "" if rest.len() == value.trim().len() && tail.is_empty() => default,
+            "" => return Err(format!("missing duration unit after '{}'", amount)),
+            suffix => Unit::from_suffix(suffix)
+                .ok_or_else(|| format!("unknown duration unit '{}'", suffix))?,
+        };
+        total = total
+            .checked_add(component(amount, unit)?)
+            .ok_or_else(|| String::from("duration is too large"))?;
+        rest = tail.trim_start();
+        if rest.is_empty() {
+            return Ok(total);
+        }
+    }
+}
+
+/// Converts a single amount of a unit into a duration
+fn component(
+    amount: &str,
+    unit: Unit,
+) -> Result<Duration, String> {
+    if let Ok(amount) = amount.parse::<u64>() {
+        let nanos = u128::from(amount) * unit.nanos();
+        let secs = u64::try_from(nanos / 1_000_000_000)
+            .map_err(|_| String::from("duration is too large"))?;
+        return Ok(Duration::new(secs, (nanos % 1_000_000_000) as u32));
+    }
+    match amount.parse::<f64>() {
+        Ok(amount) => Duration::try_from_secs_f64(amount * unit.nanos() as f64 / 1e9)
+            .map_err(|e| e.to_string()),
+        Err(_) => Err(String::from("invalid duration")),
+    }
+}
+
+struct DurationVisitor;
+
+impl<'de> de::Visitor<'de> for DurationVisitor {
+    type Value = Duration;
+
+    fn expecting(
+        &self,
+        formatter: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        formatter.write_str("a duration like 30s")
+    }
+
+    fn visit_str<E>(
+        self,
+        value: &str,
+    ) -> Result<Duration, E>
+    where
+        E: de::Error,
+    {
+        parse(value, Unit::Secs).map_err(E::custom)
+    }
+
+    fn visit_newtype_struct<D>(
+        self,
+        deserializer: D,
+    ) -> Result<Duration, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self)
+    }
+}
+
+/// Deserializes a duration from a human friendly value
+///
+/// Bare numbers are read in the unit configured with
+/// [Config::duration_default_unit](../struct.Config.html#method.duration_default_unit) when
+/// deserializing with envy, and as seconds otherwise.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    // envy's deserializers recognize the marker and provide values in nanoseconds, parsed
+    // with their default unit, while others visit the newtype and provide the raw value
+    deserializer.deserialize_newtype_struct(MARKER, DurationVisitor)
+}
+
+/// Serializes a duration into a value accepted by [deserialize](fn.deserialize.html)
+///
+/// Durations are serialized as whole seconds, like `30s`, or as nanoseconds when they
+/// include a fraction of a second.
+pub fn serialize<S>(
+    value: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if value.subsec_nanos() == 0 {
+        serializer.collect_str(&format_args!("{}s", value.as_secs()))
+    } else {
+        serializer.collect_str(&format_args!("{}ns", value.as_nanos()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Cache {
+        #[serde(with = "super")]
+        cache_ttl: Duration,
+    }
+
+    #[test]
+    fn deserializes_with_module() {
+        let data = vec![(String::from("CACHE_TTL"), String::from("30"))];
+        assert_eq!(
+            crate::from_iter::<_, Cache>(data),
+            Ok(Cache {
+                cache_ttl: Duration::from_secs(30)
+            })
+        );
+        let data = vec![(String::from("CACHE_TTL"), String::from("1h30x"))];
+        assert_eq!(
+            crate::from_iter::<_, Cache>(data),
+            Err(crate::Error::InvalidValue {
+                var: String::from("CACHE_TTL"),
+                value: String::from("1h30x"),
+                code: crate::ErrorCode::Invalid,
+                message: String::from("unknown duration unit 'x'")
+            })
+        );
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse("30", Unit::Secs), Ok(Duration::from_secs(30)));
+        assert_eq!(parse("250ms", Unit::Secs), Ok(Duration::from_millis(250)));
+        assert_eq!(parse("1.5h", Unit::Secs), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse("2 m", Unit::Secs), Ok(Duration::from_secs(120)));
+        assert_eq!(
+            parse("5 fortnights", Unit::Secs),
+            Err(String::from("unknown duration unit 'fortnights'"))
+        );
+        assert_eq!(parse("", Unit::Secs), Err(String::from("invalid duration")));
+    }
+
+    #[test]
+    fn sums_duration_components() {
+        assert_eq!(parse("1h30m", Unit::Secs), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse("90m", Unit::Secs), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse("1h30m15s", Unit::Secs), Ok(Duration::from_secs(5415)));
+        assert_eq!(
+            parse("1h 500ms", Unit::Secs),
+            Ok(Duration::from_millis(3_600_500))
+        );
+        assert_eq!(
+            parse("1h30x", Unit::Secs),
+            Err(String::from("unknown duration unit 'x'"))
+        );
+        assert_eq!(
+            parse("1h30", Unit::Secs),
+            Err(String::from("missing duration unit after '30'"))
+        );
+        assert_eq!(
+            parse("1h!", Unit::Secs),
+            Err(String::from("invalid duration"))
+        );
+    }
+}
@@ -1,15 +1,101 @@
 //! Error types
-use serde::de::Error as SerdeError;
+use serde::{de::Error as SerdeError, ser::Error as SerError};
 use std::{error::Error as StdError, fmt};
 
 /// Types of errors that may result from failed attempts
 /// to deserialize a type from env vars
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum Error {
+    /// A required var was absent
+    ///
+    /// This names the env var to set, including any prefix and suffix, i.e. `APP_DATABASE_URL`
+    /// for a `database_url` field read with the `APP_` prefix. Values deserialized with a
+    /// bare [Deserializer](../struct.Deserializer.html) name the field instead.
     MissingValue(String),
+    /// The value of a var failed to parse
+    InvalidValue {
+        /// The name of the env var providing the value
+        var: String,
+        /// The raw value
+        value: String,
+        /// The category of the failure
+        code: ErrorCode,
+        /// A description of the failure
+        message: String,
+    },
+    /// An element of a sequence failed to parse
+    SeqElement {
+        /// The name of the env var providing the sequence
+        var: String,
+        /// The position of the element within the sequence
+        index: usize,
+        /// The value of the element
+        value: String,
+        /// The category of the failure
+        code: ErrorCode,
+        /// A description of the failure
+        message: String,
+    },
+    /// An env var provided no field of the type being deserialized
+    ///
+    /// These are only produced with [`Config::deny_unknown`](../struct.Config.html#method.deny_unknown)
+    UnknownField(String),
+    /// Any other failure, described by a message
     Custom(String),
 }
 
+impl Error {
+    /// A stable code categorizing this error
+    ///
+    /// Codes allow presenting errors, i.e. translating them, without inspecting their
+    /// messages.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::MissingValue(_) => ErrorCode::MissingRequired,
+            Error::UnknownField(_) => ErrorCode::UnknownField,
+            Error::InvalidValue { code, .. } | Error::SeqElement { code, .. } => *code,
+            Error::Custom(_) => ErrorCode::Invalid,
+        }
+    }
+
+    /// The name of the env var this error concerns, if any
+    ///
+    /// This allows pointing users at the var to set or fix without matching on each variant.
+    pub fn var(&self) -> Option<&str> {
+        match self {
+            Error::MissingValue(var)
+            | Error::InvalidValue { var, .. }
+            | Error::SeqElement { var, .. }
+            | Error::UnknownField(var) => Some(var),
+            Error::Custom(_) => None,
+        }
+    }
+}
+
+/// Categories of [errors](enum.Error.html), as reported by
+/// [`Error::code`](enum.Error.html#method.code)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// A required var was not provided
+    MissingRequired,
+    /// A var provided no field, when unknown vars are denied
+    UnknownField,
+    /// A value was not a valid integer
+    InvalidInteger,
+    /// A value was an integer too large or too small for its field
+    IntegerOutOfRange,
+    /// A value was not a valid floating point number
+    InvalidFloat,
+    /// A value was not a valid boolean
+    InvalidBool,
+    /// A value was not a single character
+    InvalidChar,
+    /// A value was invalid in another way, such as an unknown enum variant
+    Invalid,
+}
+
 impl StdError for Error {}
 
 impl fmt::Display for Error {
@@ -19,11 +105,65 @@ impl fmt::Display for Error {
     ) -> fmt::Result {
         match self {
             Error::MissingValue(field) => write!(fmt, "missing value for {}", &field),
+            Error::InvalidValue {
+                var,
+                value,
+                message,
+                ..
+            } => write!(
+                fmt,
+                "{} while parsing value '{}' provided by {}",
+                message, value, var
+            ),
+            Error::SeqElement {
+                var,
+                index,
+                value,
+                message,
+                ..
+            } => write!(
+                fmt,
+                "{} while parsing value '{}' provided by element {} of {}",
+                message, value, index, var
+            ),
+            Error::UnknownField(var) => write!(fmt, "{} does not match any field", var),
             Error::Custom(ref msg) => write!(fmt, "{}", msg),
         }
     }
 }
 
+/// Displays an error, with missing values described by the
+/// [template](../struct.Config.html#method.missing_template) of a config
+///
+/// These are produced by [`Config::display_error`](../struct.Config.html#method.display_error)
+pub struct ErrorDisplay<'a> {
+    error: &'a Error,
+    template: Option<&'a str>,
+}
+
+impl<'a> ErrorDisplay<'a> {
+    pub(crate) fn new(
+        error: &'a Error,
+        template: Option<&'a str>,
+    ) -> Self {
+        ErrorDisplay { error, template }
+    }
+}
+
+impl fmt::Display for ErrorDisplay<'_> {
+    fn fmt(
+        &self,
+        fmt: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        match (self.error, self.template) {
+            (Error::MissingValue(var), Some(template)) => {
+                write!(fmt, "{}", template.replace("{var}", var))
+            }
+            (error, _) => write!(fmt, "{}", error),
+        }
+    }
+}
+
 impl SerdeError for Error {
     fn custom<T: fmt::Display>(msg: T) -> Self {
         Error::Custom(format!("{}", msg))
@@ -34,9 +174,72 @@ impl SerdeError for Error {
     }
 }
 
+impl SerError for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Custom(format!("{}", msg))
+    }
+}
+
+/// Non fatal observations made while deserializing a type from env vars
+///
+/// These are produced by
+/// [`Config::from_env_with_warnings`](../struct.Config.html#method.from_env_with_warnings)
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// A field was provided by a deprecated var
+    DeprecatedVar {
+        /// The name of the deprecated var
+        var: String,
+        /// The name of the var which should be used instead
+        replacement: String,
+    },
+    /// No var was provided for a field, so its default was used
+    ///
+    /// These are not produced for `Option` fields, which are simply left empty.
+    Defaulted {
+        /// The name of the var which was not provided
+        var: String,
+    },
+    /// The value of an optional field failed to parse, so the field was left empty
+    ///
+    /// These are only produced with
+    /// [`Config::lenient_optional`](../struct.Config.html#method.lenient_optional)
+    Discarded {
+        /// The name of the var providing the value
+        var: String,
+        /// A description of the failure
+        message: String,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(
+        &self,
+        fmt: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        match self {
+            Warning::DeprecatedVar { var, replacement } => {
+                write!(fmt, "{} is deprecated, use {} instead", var, replacement)
+            }
+            Warning::Defaulted { var } => write!(fmt, "{} is not set, using its default", var),
+            Warning::Discarded { var, message } => {
+                write!(fmt, "{} is ignored: {}", var, message)
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    pub struct Port {
+        port: u16,
+    }
 
     fn impl_std_error<E: StdError>(_: E) {}
 
@@ -46,6 +249,73 @@ mod tests {
         impl_std_error(Error::Custom("whoops".into()))
     }
 
+    #[test]
+    fn error_codes() {
+        let data = vec![(String::from("PORT"), String::from("eighty"))];
+        let error = crate::from_iter::<_, Port>(data).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::InvalidInteger);
+        let error = crate::from_iter::<_, Port>(Vec::new()).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::MissingRequired);
+        let data = vec![(String::from("PORT"), String::from("70000"))];
+        let error = crate::from_iter::<_, Port>(data).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::IntegerOutOfRange);
+        assert_eq!(
+            error,
+            Error::InvalidValue {
+                var: String::from("PORT"),
+                value: String::from("70000"),
+                code: ErrorCode::IntegerOutOfRange,
+                message: String::from("number too large to fit in target type")
+            }
+        );
+        let data = vec![(String::from("PORTS"), String::from("80,x"))];
+        let error = crate::from_iter::<_, Ports>(data).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::InvalidInteger);
+        let data = vec![(String::from("ENABLED"), String::from("maybe"))];
+        let error = crate::from_iter::<_, Toggle>(data).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::InvalidBool);
+        // values rejected by visitors are invalid without a more specific code
+        let data = vec![(String::from("WORKERS"), String::from("0"))];
+        let error = crate::from_iter::<_, Pool>(data).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.var(), Some("WORKERS"));
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    pub struct Pool {
+        workers: std::num::NonZeroU16,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    pub struct Ports {
+        ports: Vec<u16>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    pub struct Toggle {
+        enabled: bool,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    pub struct Database {
+        database_url: String,
+    }
+
+    #[test]
+    fn missing_values_name_env_vars() {
+        let error = crate::Config::new()
+            .prefix("APP_")
+            .from_iter::<_, Database>(Vec::new())
+            .unwrap_err();
+        assert_eq!(error.var(), Some("APP_DATABASE_URL"));
+        assert_eq!(error.to_string(), "missing value for APP_DATABASE_URL");
+        assert_eq!(Error::Custom("whoops".into()).var(), None);
+    }
+
     #[test]
     fn error_display() {
         assert_eq!(
@@ -53,6 +323,43 @@ mod tests {
             "missing value for FOO_BAR"
         );
 
+        assert_eq!(
+            format!(
+                "{}",
+                ErrorDisplay::new(
+                    &Error::MissingValue("FOO_BAR".into()),
+                    Some("{var} is not set")
+                )
+            ),
+            "FOO_BAR is not set"
+        );
+        assert_eq!(
+            format!(
+                "{}",
+                ErrorDisplay::new(&Error::Custom("whoops".into()), Some("{var} is not set"))
+            ),
+            "whoops"
+        );
+
+        assert_eq!(
+            format!(
+                "{}",
+                Error::SeqElement {
+                    var: "FOO_BAR".into(),
+                    index: 2,
+                    value: "three".into(),
+                    code: ErrorCode::InvalidInteger,
+                    message: "invalid digit found in string".into()
+                }
+            ),
+            "invalid digit found in string while parsing value 'three' provided by element 2 of FOO_BAR"
+        );
+
+        assert_eq!(
+            format!("{}", Error::UnknownField("APP_DATABSE_URL".into())),
+            "APP_DATABSE_URL does not match any field"
+        );
+
         assert_eq!(format!("{}", Error::Custom("whoops".into())), "whoops")
     }
 }
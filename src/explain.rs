@@ -0,0 +1,548 @@
+//! Describes the env vars a type reads without deserializing it
+use crate::{dotenv, duration, Config, Error, Result};
+use serde::de::{self, value::SeqDeserializer, IntoDeserializer};
+use std::{cell::RefCell, collections::HashSet, iter::empty, path::Path};
+
+/// An env var a type is read from
+///
+/// These are produced by [`Config::field_specs`](struct.Config.html#method.field_specs)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldSpec {
+    /// The name of the env var
+    pub name: String,
+    /// Whether the var may be absent, i.e. because it provides an `Option` field
+    pub optional: bool,
+    /// Whether the var provides a field with a serde default, which is used when it is absent
+    pub defaulted: bool,
+}
+
+/// A dry-run deserializer recording the env var each value would be read from
+struct Explainer<'a> {
+    config: &'a Config,
+    /// Each var recorded along with its description
+    lines: &'a RefCell<Vec<(FieldSpec, String)>>,
+    /// The name of the env var this value is read from, or `None` for the type itself
+    var: Option<String>,
+    optional: bool,
+    /// Whether this value is part of another value, i.e. a tuple component
+    quiet: bool,
+    /// The var left out of this run, to find whether its field has a default
+    omitted: Option<&'a str>,
+    /// The vars of fields found to have a default
+    defaulted: &'a HashSet<String>,
+}
+
+impl<'a> Explainer<'a> {
+    fn child(
+        &self,
+        var: String,
+    ) -> Self {
+        Explainer {
+            config: self.config,
+            lines: self.lines,
+            var: Some(var),
+            optional: false,
+            quiet: self.quiet,
+            omitted: self.omitted,
+            defaulted: self.defaulted,
+        }
+    }
+
+    /// The name of the env var this value is read from, including any suffix
+    fn var_name(&self) -> Option<String> {
+        let suffix = self.config.suffix.as_deref().unwrap_or_default();
+        self.var.as_ref().map(|var| format!("{}{}", var, suffix))
+    }
+
+    /// Records the kind of value read from this var along with any transforms applied to it
+    fn record(
+        &self,
+        kind: &str,
+    ) {
+        let var = match (&self.var, self.quiet) {
+            (Some(var), false) => var,
+            _ => return,
+        };
+        let options = &self.config.options;
+        let var_name = self.var_name().unwrap_or_default();
+        let defaulted = !self.optional && self.defaulted.contains(&var_name);
+        let mut line = format!("{}: {}", var_name, kind);
+        line.push_str(match (self.optional, defaulted) {
+            (true, _) => ", optional",
+            (false, true) => ", has a default",
+            (false, false) => ", required",
+        });
+        if let (true, Some(null)) = (self.optional, &options.global_null) {
+            line.push_str(&format!(", '{}' means none", null));
+        }
+        let name = var
+            .strip_prefix(self.config.prefix.as_deref().unwrap_or_default())
+            .unwrap_or(var);
+        if options
+            .transforms
+            .iter()
+            .any(|(field, _)| field.eq_ignore_ascii_case(name))
+        {
+            line.push_str(", value is transformed");
+        }
+        if !options.value_aliases.is_empty() {
+            line.push_str(", values are aliased");
+        }
+        let spec = FieldSpec {
+            name: var_name,
+            optional: self.optional,
+            defaulted,
+        };
+        self.lines.borrow_mut().push((spec, line));
+    }
+}
+
+macro_rules! explain_values {
+    ($($method:ident => $kind:literal $visit:ident($($value:expr)?),)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+                where V: de::Visitor<'de>
+            {
+                self.record($kind);
+                visitor.$visit($($value)?)
+            }
+        )*
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for Explainer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.record("string");
+        visitor.visit_str("")
+    }
+
+    fn deserialize_bool<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let options = &self.config.options;
+        self.record(&match (&options.bool_tokens, options.pg_bools) {
+            (Some((truthy, falsy)), _) => format!(
+                "bool accepting {} as true and {} as false",
+                truthy.join("/"),
+                falsy.join("/")
+            ),
+            (None, true) => String::from("bool accepting t/f, yes/no, on/off and 1/0"),
+            (None, false) => String::from("bool"),
+        });
+        visitor.visit_bool(false)
+    }
+
+    explain_values! {
+        deserialize_u8 => "u8" visit_u8(0),
+        deserialize_u16 => "u16" visit_u16(0),
+        deserialize_u32 => "u32" visit_u32(0),
+        deserialize_u64 => "u64" visit_u64(0),
+        deserialize_u128 => "u128" visit_u128(0),
+        deserialize_i8 => "i8" visit_i8(0),
+        deserialize_i16 => "i16" visit_i16(0),
+        deserialize_i32 => "i32" visit_i32(0),
+        deserialize_i64 => "i64" visit_i64(0),
+        deserialize_i128 => "i128" visit_i128(0),
+        deserialize_f32 => "f32" visit_f32(0.0),
+        deserialize_f64 => "f64" visit_f64(0.0),
+        deserialize_char => "char" visit_char('\0'),
+        deserialize_str => "string" visit_str(""),
+        deserialize_string => "string" visit_str(""),
+        deserialize_bytes => "bytes" visit_bytes(&[]),
+        deserialize_byte_buf => "bytes" visit_bytes(&[]),
+        deserialize_unit => "unit" visit_unit(),
+        deserialize_identifier => "identifier" visit_str(""),
+    }
+
+    fn deserialize_option<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_some(Explainer {
+            optional: true,
+            ..self
+        })
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if name == duration::MARKER {
+            self.record(&format!(
+                "duration, bare numbers are {:?}",
+                self.config.options.duration_unit
+            ));
+            return visitor.visit_str("0s");
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let options = &self.config.options;
+        self.record(&if options.csv {
+            String::from("list of comma separated values which may be double quoted")
+        } else if options.auto_separator {
+            String::from("list separated by one of ',', ';' or '|'")
+        } else {
+            format!("list separated by '{}'", options.separator.unwrap_or(','))
+        });
+        visitor.visit_seq(SeqDeserializer::new(empty::<Explainer>()))
+    }
+
+    fn deserialize_tuple<V>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.record(&format!("{} components separated by ':'", len));
+        let components = (0..len).map(|_| Explainer {
+            quiet: true,
+            ..self.child(String::new())
+        });
+        visitor.visit_seq(SeqDeserializer::new(components))
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match &self.var {
+            Some(var) if self.config.options.nested => {
+                let var = format!("{}{}*", var, self.config.options.nesting_separator());
+                self.child(var).record("map entry");
+            }
+            Some(_) => self.record(&format!(
+                "map of key:value entries separated by '{}'",
+                self.config.options.separator.unwrap_or(',')
+            )),
+            None => {}
+        }
+        visitor.visit_map(de::value::MapDeserializer::new(
+            empty::<(String, Explainer)>(),
+        ))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let prefix = match &self.var {
+            None => self.config.prefix.clone().unwrap_or_default(),
+            Some(var) if self.config.options.nested => {
+                format!("{}{}", var, self.config.options.nesting_separator())
+            }
+            Some(_) => {
+                self.record("struct, which requires nested vars");
+                return Err(de::Error::custom("structs require nested vars"));
+            }
+        };
+        let keep_names = self.config.options.keep_names;
+        let entries = fields
+            .iter()
+            .map(|field| {
+                let name = if keep_names {
+                    field.to_string()
+                } else {
+                    field.to_uppercase()
+                };
+                (*field, self.child(format!("{}{}", prefix, name)))
+            })
+            .filter(|(_, child)| child.var_name().as_deref() != self.omitted);
+        visitor.visit_map(de::value::MapDeserializer::new(entries))
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.record(&format!("one of {}", variants.join(", ")));
+        match variants.first() {
+            Some(variant) => visitor.visit_enum(variant.into_deserializer()),
+            None => Err(de::Error::custom("enum has no variants")),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+}
+
+impl<'de, 'a> IntoDeserializer<'de, Error> for Explainer<'a> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl Config {
+    /// Describes the env vars a type would be read from, one line per var
+    ///
+    /// Each line names the env var, the kind of value expected, whether it is required,
+    /// optional or has a default and any transforms applied to its value. No env vars are read. The description stops
+    /// at values which can not be explained, such as enum variants holding data.
+    pub fn explain<T>(&self) -> String
+    where
+        T: de::DeserializeOwned,
+    {
+        self.explained::<T>()
+            .into_iter()
+            .map(|(_, line)| line + "\n")
+            .collect()
+    }
+
+    /// Lists the env vars a type would be read from and whether each is optional
+    ///
+    /// Like [explain](#method.explain), no env vars are read. This suits tooling such as
+    /// generators of `.env.example` files. Fields with a serde default are found by leaving
+    /// each var out of a dry run, so they are only reported as defaulted when the rest of
+    /// the type can be explained, i.e. it holds no enum variants with data.
+    pub fn field_specs<T>(&self) -> Vec<FieldSpec>
+    where
+        T: de::DeserializeOwned,
+    {
+        self.explained::<T>()
+            .into_iter()
+            .map(|(spec, _)| spec)
+            .collect()
+    }
+
+    /// Lists the required env vars of a type which a dotenv style example file, such as
+    /// `.env.example`, does not mention
+    ///
+    /// Vars of optional fields and fields with a serde default, as reported by
+    /// [field_specs](#method.field_specs), are not required.
+    ///
+    /// This keeps example files documenting every required var honest. Names are compared
+    /// ignoring case and the values of the file are ignored. Fails if the file can not be read.
+    pub fn check_against_example<T, P>(
+        &self,
+        path: P,
+    ) -> Result<Vec<String>>
+    where
+        T: de::DeserializeOwned,
+        P: AsRef<Path>,
+    {
+        let example = dotenv::read(path.as_ref())?;
+        Ok(self
+            .field_specs::<T>()
+            .into_iter()
+            .filter(|spec| {
+                !spec.optional
+                    && !spec.defaulted
+                    && !example
+                        .iter()
+                        .any(|(name, _)| name.eq_ignore_ascii_case(&spec.name))
+            })
+            .map(|spec| spec.name)
+            .collect())
+    }
+
+    fn explained<T>(&self) -> Vec<(FieldSpec, String)>
+    where
+        T: de::DeserializeOwned,
+    {
+        let run = |omitted: Option<&str>, defaulted: &HashSet<String>| {
+            let lines = RefCell::new(Vec::new());
+            let result = T::deserialize(Explainer {
+                config: self,
+                lines: &lines,
+                var: None,
+                optional: false,
+                quiet: false,
+                omitted,
+                defaulted,
+            });
+            (result.is_ok(), lines.into_inner())
+        };
+        let (_, lines) = run(None, &HashSet::new());
+        // a required var whose absence still lets the type be read provides a field with
+        // a default
+        let defaulted = lines
+            .iter()
+            .filter(|(spec, _)| !spec.optional)
+            .filter(|(spec, _)| run(Some(&spec.name), &HashSet::new()).0)
+            .map(|(spec, _)| spec.name.clone())
+            .collect::<HashSet<_>>();
+        if defaulted.is_empty() {
+            return lines;
+        }
+        run(None, &defaulted).1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::time::Duration;
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    pub struct Database {
+        host: String,
+        port: Option<u16>,
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    pub struct App {
+        debug: bool,
+        tags: Vec<String>,
+        #[serde(with = "crate::duration")]
+        timeout: Duration,
+        db: Database,
+    }
+
+    #[test]
+    fn explains_nested_vars() {
+        assert_eq!(
+            Config::new()
+                .prefix("APP_")
+                .nested(true)
+                .global_null("__NULL__")
+                .transform_value("db__host", str::to_lowercase)
+                .explain::<App>(),
+            "APP_DEBUG: bool, required\n\
+             APP_TAGS: list separated by ',', required\n\
+             APP_TIMEOUT: duration, bare numbers are Secs, required\n\
+             APP_DB__HOST: string, required, value is transformed\n\
+             APP_DB__PORT: u16, optional, '__NULL__' means none\n"
+        );
+    }
+
+    #[test]
+    fn reports_vars_missing_from_example() {
+        let path = std::env::temp_dir().join(format!("envy-example-{}.env", std::process::id()));
+        std::fs::write(&path, "# the database\nAPP_DB__PORT=5432\napp_debug=\n")
+            .expect("failed to write example");
+        let missing = Config::new()
+            .prefix("APP_")
+            .nested(true)
+            .check_against_example::<App, _>(&path);
+        std::fs::remove_file(&path).expect("failed to remove example");
+        assert_eq!(
+            missing,
+            Ok(vec![
+                String::from("APP_TAGS"),
+                String::from("APP_TIMEOUT"),
+                String::from("APP_DB__HOST")
+            ])
+        );
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    pub struct Server {
+        host: String,
+        #[serde(default)]
+        port: u16,
+        db: Database,
+    }
+
+    #[test]
+    fn reports_defaulted_fields() {
+        let config = Config::new().nested(true);
+        assert_eq!(
+            config.explain::<Server>(),
+            "HOST: string, required\n\
+             PORT: u16, has a default\n\
+             DB__HOST: string, required\n\
+             DB__PORT: u16, optional\n"
+        );
+        let path = std::env::temp_dir().join(format!("envy-defaults-{}.env", std::process::id()));
+        std::fs::write(
+            &path,
+            "DB__HOST=localhost
+",
+        )
+        .expect("failed to write example");
+        let missing = config.check_against_example::<Server, _>(&path);
+        std::fs::remove_file(&path).expect("failed to remove example");
+        assert_eq!(missing, Ok(vec![String::from("HOST")]));
+    }
+
+    #[test]
+    fn lists_field_specs() {
+        let spec = |name: &str, optional| FieldSpec {
+            name: String::from(name),
+            optional,
+            defaulted: false,
+        };
+        assert_eq!(
+            Config::new().field_specs::<Database>(),
+            vec![spec("HOST", false), spec("PORT", true)]
+        );
+    }
+}
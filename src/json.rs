@@ -0,0 +1,163 @@
+//! Support for fields holding JSON documents
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+/// A value read from an env var holding a JSON document
+///
+/// This suits structured fields, such as maps or lists of objects, which separated values
+/// can not express. Errors name the var holding the document.
+///
+/// # Example
+///
+/// ```
+/// use envy::json::Json;
+/// use serde::Deserialize;
+/// use std::collections::HashMap;
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     limits: Json<HashMap<String, Vec<u32>>>,
+/// }
+///
+/// let config: Config = envy::from_iter(vec![(
+///     String::from("LIMITS"),
+///     String::from(r#"{"a":[1],"b":[2,3]}"#),
+/// )])
+/// .unwrap();
+/// assert_eq!(config.limits["b"], vec![2, 3]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Json<T>(pub T);
+
+impl<T> Json<T> {
+    /// Unwraps the parsed value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Json<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Json<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+struct JsonVisitor<T>(PhantomData<T>);
+
+impl<'de, T> de::Visitor<'de> for JsonVisitor<T>
+where
+    T: de::DeserializeOwned,
+{
+    type Value = Json<T>;
+
+    fn expecting(
+        &self,
+        formatter: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        formatter.write_str("a JSON document")
+    }
+
+    fn visit_str<E>(
+        self,
+        value: &str,
+    ) -> Result<Json<T>, E>
+    where
+        E: de::Error,
+    {
+        serde_json::from_str(value).map(Json).map_err(E::custom)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Json<T>
+where
+    T: de::DeserializeOwned,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Json<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(JsonVisitor(PhantomData))
+    }
+}
+
+impl<T> Serialize for Json<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let document = serde_json::to_string(&self.0).map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, ErrorCode};
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    pub struct Backend {
+        host: String,
+        weight: u8,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    pub struct Proxy {
+        backends: Json<Vec<Backend>>,
+    }
+
+    #[test]
+    fn deserializes_json_documents() {
+        let data = vec![(
+            String::from("BACKENDS"),
+            String::from(r#"[{"host":"a","weight":1},{"host":"b","weight":2}]"#),
+        )];
+        let proxy = crate::from_iter::<_, Proxy>(data.clone()).unwrap();
+        assert_eq!(
+            *proxy.backends,
+            vec![
+                Backend {
+                    host: String::from("a"),
+                    weight: 1
+                },
+                Backend {
+                    host: String::from("b"),
+                    weight: 2
+                }
+            ]
+        );
+        assert_eq!(crate::to_vec(&proxy), Ok(data));
+    }
+
+    #[test]
+    fn fails_with_invalid_json() {
+        let data = vec![(String::from("BACKENDS"), String::from("[{"))];
+        assert_eq!(
+            crate::from_iter::<_, Proxy>(data),
+            Err(Error::InvalidValue {
+                var: String::from("BACKENDS"),
+                value: String::from("[{"),
+                code: ErrorCode::Invalid,
+                message: String::from("EOF while parsing an object at line 1 column 2")
+            })
+        );
+    }
+}
@@ -28,10 +28,18 @@
 //! ```
 //!
 //! Special treatment is given to collections. For config fields that store a `Vec` of values,
-//! use an env var that uses a comma separated value.
+//! use an env var that uses a comma separated value. Commas within an element may be escaped
+//! with a backslash, so `a\,b,c` provides the elements `a,b` and `c`.
 //!
 //! All serde modifiers should work as is.
 //!
+//! Values read with [from_env](fn.from_env.html) or [from_iter](fn.from_iter.html) are owned
+//! by envy while deserializing, so fields can not borrow from them. Use owned types like
+//! `String` in place of `&str` there. `Cow<'static, str>` fields are supported and are always
+//! deserialized into their owned variant. To borrow values, read them from borrowed
+//! `(&str, &str)` pairs with [from_iter_borrowed](fn.from_iter_borrowed.html), where `&str`
+//! and `Cow<str>` fields borrow from the input.
+//!
 //! Enums with unit variants can be used as values:
 //!
 //! ```no_run
@@ -57,40 +65,646 @@
 //! }
 //! ```
 
-use serde::de::{
-    self,
-    value::{MapDeserializer, SeqDeserializer},
-    IntoDeserializer,
+use serde::{
+    de::{
+        value::{BorrowedStrDeserializer, MapDeserializer, SeqDeserializer, StrDeserializer},
+        IntoDeserializer,
+    },
+    Serialize,
 };
 use std::{
     borrow::Cow,
+    cell::{Cell, RefCell},
+    char::ParseCharError,
+    collections::{BTreeMap, HashMap, HashSet},
     env,
+    ffi::OsString,
+    fmt, fs,
     iter::{empty, IntoIterator},
+    marker::PhantomData,
+    num::{IntErrorKind, ParseFloatError, ParseIntError},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{Arc, Mutex},
 };
 
 // Ours
+pub mod bytesize;
+mod content;
+pub mod de;
+mod dotenv;
+pub mod duration;
 mod error;
-pub use crate::error::Error;
+mod explain;
+#[cfg(feature = "json")]
+pub mod json;
+mod macros;
+#[cfg(feature = "jsonschema")]
+mod schema;
+mod secret;
+mod ser;
+#[cfg(feature = "config")]
+mod source;
+mod value;
+use crate::content::Content;
+#[cfg(feature = "config")]
+pub use crate::source::ConfigSource;
+pub use crate::{
+    duration::Unit,
+    error::{Error, ErrorCode, ErrorDisplay, Warning},
+    explain::FieldSpec,
+    secret::Secret,
+    value::Value,
+};
 
 /// A type result type specific to `envy::Errors`
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Default)]
-struct VarsOptions {
-    keep_names: bool,
+/// A function supplying values for struct fields absent from the env
+type Resolver = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// A source of values for vars absent from the env, such as a secrets backend
+///
+/// Sources are set with [Config::fallback_source](struct.Config.html#method.fallback_source)
+/// and are only queried for fields whose var is absent.
+pub trait FallbackSource: Send + Sync {
+    /// Gets the value of the named var, if the source provides one
+    fn get(
+        &self,
+        var: &str,
+    ) -> Option<String>;
 }
 
-struct Vars<Iter>
+impl<F> FallbackSource for F
 where
-    Iter: IntoIterator<Item = (String, String)>,
+    F: Fn(&str) -> Option<String> + Send + Sync,
 {
+    fn get(
+        &self,
+        var: &str,
+    ) -> Option<String> {
+        self(var)
+    }
+}
+
+/// A function rewriting the raw value of a field before it is parsed
+type Transform = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/// A function decrypting sealed values, returning `None` when they can not be decrypted
+type Decrypt = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// The provenance of each field, recorded by field name
+type ProvenanceLog = Arc<Mutex<Vec<(String, Provenance)>>>;
+
+/// Where a struct field comes from, or `None` when it is read from a namespace of nested vars
+type Source = (&'static str, Option<Provenance>);
+
+#[derive(Default, Clone)]
+struct VarsOptions {
+    keep_names: bool,
+    nested: bool,
+    normalize_separators: bool,
+    resolver: Option<Resolver>,
+    pg_bools: bool,
+    sensitive: Vec<String>,
+    audit: Option<Arc<Mutex<Vec<AuditEntry>>>>,
+    case_insensitive: bool,
+    csv: bool,
+    value_aliases: HashMap<String, String>,
+    case_insensitive_enums: bool,
+    global_null: Option<String>,
+    duration_unit: Unit,
+    transforms: Vec<(String, Transform)>,
+    #[cfg(feature = "json")]
+    json_arrays: bool,
+    reject_control_chars: bool,
+    max_value_len: Option<usize>,
+    /// Deprecated var names along with the names of the fields they provide
+    deprecated: Vec<(String, String)>,
+    warnings: Option<Arc<Mutex<Vec<Warning>>>>,
+    group_separator: Option<String>,
+    empty_bool_true: bool,
+    separator: Option<char>,
+    max_depth: Option<usize>,
+    nesting_separator: Option<String>,
+    unquote: bool,
+    no_unquote: Vec<String>,
+    deny_unknown: bool,
+    /// Normalized names of vars tolerated when unknown vars are denied
+    allowed_unknown: Vec<String>,
+    auto_separator: bool,
+    /// Additional tokens accepted as `true` and `false`
+    bool_tokens: Option<(Vec<String>, Vec<String>)>,
+    provenance: Option<ProvenanceLog>,
+    lenient_optional: bool,
+    percent_decode_keys: bool,
+    /// Characters removed from env var names before they are matched
+    key_strip_chars: Vec<char>,
+    empty_as_absent: bool,
+    /// Invalid values of optional fields, recorded while reading leniently
+    discarded: Option<Arc<Mutex<Vec<Warning>>>>,
+    /// Maps env var names to field names in place of lowercasing them
+    key_map: Option<Transform>,
+    /// The marker of sealed values along with the function decrypting them
+    decrypt: Option<(String, Decrypt)>,
+    /// Fields filled with an empty value, rather than failing, when their vars are absent
+    soft_missing: Vec<String>,
+    /// The soft missing fields filled while deserializing
+    filled: Option<Arc<Mutex<Vec<&'static str>>>>,
+    /// The map field collecting vars which provide no other field
+    remaining: Option<String>,
+    /// The name of the var selecting the variant of enums read from nested vars
+    enum_method: Option<String>,
+    /// Values filling fields whose vars are absent, while reading infallibly
+    defaults: Option<Arc<Defaults>>,
+}
+
+/// The captured `Default` of a type, along with the fields it fills
+struct Defaults {
+    content: Content,
+    /// The names of the filled fields, separated like nested vars, i.e. `database__host`
+    filled: HashSet<String>,
+}
+
+impl Defaults {
+    /// The names of every field of the default, separated like nested vars
+    fn paths(
+        content: &Content,
+        path: &str,
+        options: &VarsOptions,
+    ) -> Vec<String> {
+        content
+            .fields()
+            .flat_map(|(field, value)| {
+                let var = format!("{}{}", path, options.normalize(field));
+                let nested = format!("{}{}", var, options.nesting_separator());
+                let mut paths = Defaults::paths(value, &nested, options);
+                paths.insert(0, var);
+                paths
+            })
+            .collect()
+    }
+
+    /// Adds the filled fields of `content` absent from `children`
+    fn fill<'de>(
+        &self,
+        children: &mut Vec<(String, Node<'de>)>,
+        content: &Content,
+        path: &str,
+        options: &VarsOptions,
+    ) {
+        for (field, value) in content.fields() {
+            let name = options.normalize(field);
+            let var = format!("{}{}", path, name);
+            match children.iter_mut().find(|(child, _)| *child == name) {
+                Some((_, Node::Map(_, children))) => {
+                    let path = format!("{}{}", var, options.nesting_separator());
+                    self.fill(children, value, &path, options);
+                }
+                Some(_) => {}
+                None if self.filled.contains(&var) => {
+                    children.push((field.to_owned(), Node::Default(value.clone())));
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+impl VarsOptions {
+    /// Normalizes an env var name into the name of the field it provides
+    fn normalize(
+        &self,
+        name: &str,
+    ) -> String {
+        let name = if let Some(key_map) = &self.key_map {
+            key_map(name)
+        } else if self.keep_names {
+            name.to_owned()
+        } else {
+            name.to_lowercase()
+        };
+        if self.normalize_separators {
+            name.replace(['.', '-'], "_")
+        } else {
+            name
+        }
+    }
+
+    /// The separator splitting the names of nested vars
+    fn nesting_separator(&self) -> &str {
+        self.nesting_separator
+            .as_deref()
+            .unwrap_or(NESTING_SEPARATOR)
+    }
+
+    /// Determines where each field of a struct comes from, when warnings or provenance are
+    /// being recorded
+    fn sources(
+        &self,
+        fields: &'static [&'static str],
+        entries: &[(String, Node)],
+        resolved: &[&str],
+    ) -> Vec<Source> {
+        if self.warnings.is_none() && self.provenance.is_none() {
+            return Vec::new();
+        }
+        fields
+            .iter()
+            .map(|field| {
+                let source = match entries.iter().find(|(name, _)| name == field) {
+                    _ if resolved.contains(field) => Some(Provenance::Fallback(field.to_string())),
+                    Some((_, Node::Map(..))) => None,
+                    Some(_) => Some(Provenance::Env(field.to_string())),
+                    None => Some(Provenance::Default),
+                };
+                (*field, source)
+            })
+            .collect()
+    }
+
+    /// The lengths of the warning and provenance logs, marking where reading a struct began
+    fn recorded(&self) -> (usize, usize) {
+        let warnings = self.warnings.as_ref().and_then(|log| log.lock().ok());
+        let provenance = self.provenance.as_ref().and_then(|log| log.lock().ok());
+        (
+            warnings.map_or(0, |log| log.len()),
+            provenance.map_or(0, |log| log.len()),
+        )
+    }
+
+    /// Records the sources of the fields of a struct once read, then names them, along with
+    /// the fields of nested structs recorded since `start`, by their path
+    fn record(
+        &self,
+        path: &str,
+        start: (usize, usize),
+        sources: Vec<Source>,
+    ) {
+        if let Some(Ok(mut log)) = self.provenance.as_ref().map(|log| log.lock()) {
+            for (field, source) in &sources {
+                let nested = format!("{}{}", field, self.nesting_separator());
+                let source = match source {
+                    Some(source) => source.clone(),
+                    // namespaces recording no fields, like maps, are reported as provided by
+                    // the namespace itself
+                    None if !log[start.1..]
+                        .iter()
+                        .any(|(name, _)| name.starts_with(&nested)) =>
+                    {
+                        Provenance::Env(field.to_string())
+                    }
+                    None => continue,
+                };
+                log.push((field.to_string(), source));
+            }
+            for (name, source) in log[start.1..].iter_mut() {
+                *name = format!("{}{}", path, name);
+                if let Provenance::Env(var) | Provenance::Fallback(var) = source {
+                    *var = format!("{}{}", path, var);
+                }
+            }
+        }
+        if let Some(Ok(mut log)) = self.warnings.as_ref().map(|log| log.lock()) {
+            for (field, source) in &sources {
+                if let Some(Provenance::Default) = source {
+                    log.push(Warning::Defaulted {
+                        var: field.to_uppercase(),
+                    });
+                }
+            }
+            for warning in log[start.0..].iter_mut() {
+                if let Warning::Defaulted { var } = warning {
+                    *var = format!("{}{}", path, var).to_uppercase();
+                }
+            }
+        }
+    }
+
+    /// Parses a `bool` value, accepting the configured shorthands
+    fn parse_bool(
+        &self,
+        value: &str,
+    ) -> std::result::Result<bool, String> {
+        if self.empty_bool_true && value.is_empty() {
+            return Ok(true);
+        }
+        if let Some((truthy, falsy)) = &self.bool_tokens {
+            let value = value.trim();
+            if truthy.iter().any(|token| token.eq_ignore_ascii_case(value)) {
+                return Ok(true);
+            }
+            if falsy.iter().any(|token| token.eq_ignore_ascii_case(value)) {
+                return Ok(false);
+            }
+        }
+        if self.pg_bools {
+            return match value.to_lowercase().as_str() {
+                "t" | "true" | "yes" | "on" | "1" => Ok(true),
+                "f" | "false" | "no" | "off" | "0" => Ok(false),
+                _ => Err(String::from("provided string was not a recognized boolean")),
+            };
+        }
+        value.parse::<bool>().map_err(|e| e.to_string())
+    }
+
+    /// Rewrites a value to its canonical form when it has a configured alias
+    fn alias<'v>(
+        &self,
+        value: Cow<'v, str>,
+    ) -> Cow<'v, str> {
+        match self.value_aliases.get(&*value) {
+            Some(canonical) => Cow::Owned(canonical.clone()),
+            None => value,
+        }
+    }
+}
+
+/// A record of a value seen while deserializing a field
+///
+/// These are produced by [`Config::from_env_audited`](struct.Config.html#method.from_env_audited)
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    /// The name of the env var providing the value
+    pub var: String,
+    /// The provided value, or `***` when the var is configured as
+    /// [sensitive](struct.Config.html#method.sensitive)
+    pub value: String,
+    /// Whether the value was successfully parsed
+    pub parsed_ok: bool,
+}
+
+/// Where the value of a field came from
+///
+/// These are produced by
+/// [`Config::from_env_with_provenance`](struct.Config.html#method.from_env_with_provenance)
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Provenance {
+    /// The value was provided by the named env var
+    Env(String),
+    /// The value was provided by the named var of a dotenv file
+    File {
+        /// The name of the var providing the value
+        var: String,
+        /// The path of the file defining the var
+        path: PathBuf,
+    },
+    /// The named var was absent, so the value was supplied by a
+    /// [resolver](struct.Config.html#method.resolve) or
+    /// [fallback source](struct.Config.html#method.fallback_source)
+    Fallback(String),
+    /// No value was provided, so the field was left to its default
+    Default,
+}
+
+struct Vars<Iter> {
     inner: Iter,
-    options: VarsOptions,
+    options: Rc<VarsOptions>,
+}
+
+/// The value of a var, borrowed from the input where possible
+struct Val<'de> {
+    name: String,
+    value: Cow<'de, str>,
+    /// The position of this value within a sequence
+    index: Option<usize>,
+    options: Rc<VarsOptions>,
 }
 
-struct Val(String, String);
+impl<'de> Val<'de> {
+    fn new(
+        name: String,
+        value: Cow<'de, str>,
+        options: Rc<VarsOptions>,
+    ) -> Self {
+        Val {
+            name,
+            value: options.alias(value),
+            index: None,
+            options,
+        }
+    }
+
+    /// Produces a value for a component of this value
+    fn component(
+        &self,
+        index: Option<usize>,
+        value: String,
+    ) -> Self {
+        Val {
+            name: self.name.clone(),
+            value: self.options.alias(Cow::Owned(value)),
+            index,
+            options: self.options.clone(),
+        }
+    }
+
+    /// Applies `f` to this value once validated, recording the outcome when an audit log is
+    /// being kept
+    fn audit<T>(
+        self,
+        f: impl FnOnce(Self) -> Result<T>,
+    ) -> Result<T> {
+        if self.options.reject_control_chars && self.value.chars().any(|c| c.is_ascii_control()) {
+            return Err(Error::Custom(format!(
+                "value provided by {} contains control characters",
+                self.name
+            )));
+        }
+        if let Some(max) = self
+            .options
+            .max_value_len
+            .filter(|max| self.value.len() > *max)
+        {
+            return Err(Error::Custom(format!(
+                "value provided by {} exceeds the maximum length of {} bytes",
+                self.name, max
+            )));
+        }
+        let log = match &self.options.audit {
+            Some(log) => log.clone(),
+            None => return f(self.decrypted()?),
+        };
+        // names and sensitive values are resolved by the config, which knows the affixes
+        let var = self.name.clone();
+        let value = self.value.to_string();
+        let result = self.decrypted().and_then(f);
+        if let Ok(mut log) = log.lock() {
+            log.push(AuditEntry {
+                var,
+                value,
+                parsed_ok: result.is_ok(),
+            });
+        }
+        result
+    }
+
+    /// Decrypts this value if it is sealed, naming only the var when that fails
+    fn decrypted(mut self) -> Result<Self> {
+        if let Some((marker, decrypt)) = &self.options.decrypt {
+            if let Some(sealed) = self.value.strip_prefix(marker.as_str()) {
+                let value = decrypt(sealed).ok_or_else(|| {
+                    Error::Custom(format!("failed to decrypt value provided by {}", self.name))
+                })?;
+                self.value = Cow::Owned(value);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Splits this value into comma separated elements, honoring double quoted
+    /// elements which may contain commas and `""` escaped quotes.
+    ///
+    /// Unquoted empty elements are dropped while quoted empty elements are kept
+    fn split_csv(&self) -> Result<Vec<String>> {
+        let mut elements = Vec::new();
+        let mut chars = self.value.chars().peekable();
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            let mut element = String::new();
+            if chars.next_if_eq(&'"').is_some() {
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.next_if_eq(&'"').is_some() => element.push('"'),
+                        Some('"') => break,
+                        Some(c) => element.push(c),
+                        None => return Err(self.error("unterminated quoted element")),
+                    }
+                }
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                elements.push(element);
+                match chars.next() {
+                    Some(',') => continue,
+                    None => break,
+                    Some(c) => {
+                        return Err(self.error(format_args!(
+                            "unexpected character '{}' after quoted element",
+                            c
+                        )))
+                    }
+                }
+            }
+            let mut last = true;
+            for c in chars.by_ref() {
+                if c == ',' {
+                    last = false;
+                    break;
+                }
+                element.push(c);
+            }
+            let element = element.trim();
+            if !element.is_empty() {
+                elements.push(element.to_owned());
+            }
+            if last {
+                break;
+            }
+        }
+        Ok(elements)
+    }
+
+    /// Splits this value into elements on each `separator` not escaped by a backslash.
+    ///
+    /// `\\` represents a literal backslash, while backslashes before other characters are
+    /// kept as is
+    fn split_escaped(
+        &self,
+        separator: char,
+    ) -> Vec<String> {
+        let mut elements = Vec::new();
+        let mut element = String::new();
+        let mut chars = self.value.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.clone().next() {
+                    Some(escaped) if escaped == separator || escaped == '\\' => {
+                        chars.next();
+                        element.push(escaped);
+                    }
+                    _ => element.push(c),
+                },
+                c if c == separator => elements.push(std::mem::take(&mut element)),
+                c => element.push(c),
+            }
+        }
+        elements.push(element);
+        elements
+    }
+
+    /// The character separating the elements of this value
+    ///
+    /// When separators are detected, this is whichever of `,`, `;` or `|` the value
+    /// contains unescaped, failing when it contains more than one of them
+    fn separator(&self) -> Result<char> {
+        if !self.options.auto_separator {
+            return Ok(self.options.separator.unwrap_or(','));
+        }
+        let mut found = Vec::new();
+        let mut chars = self.value.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                ',' | ';' | '|' if !found.contains(&c) => found.push(c),
+                _ => {}
+            }
+        }
+        let mut found = found.into_iter();
+        match (found.next(), found.next()) {
+            (Some(first), Some(second)) => Err(self.error(format_args!(
+                "ambiguous separator, found both '{}' and '{}'",
+                first, second
+            ))),
+            (first, _) => Ok(first.unwrap_or(',')),
+        }
+    }
+
+    /// Adds the name and value of this var to errors produced by visitors
+    fn context(
+        &self,
+        error: Error,
+    ) -> Error {
+        match error {
+            Error::Custom(msg) => self.error(msg),
+            error => error,
+        }
+    }
+
+    /// Produces an error describing a failure to parse this value
+    fn error<E: fmt::Display>(
+        &self,
+        e: E,
+    ) -> Error {
+        self.invalid(ErrorCode::Invalid, e)
+    }
+
+    /// Produces an error describing a failure to parse this value, categorized by `code`
+    fn invalid<E: fmt::Display>(
+        &self,
+        code: ErrorCode,
+        e: E,
+    ) -> Error {
+        match self.index {
+            Some(index) => Error::SeqElement {
+                var: self.name.clone(),
+                index,
+                value: self.value.to_string(),
+                code,
+                message: e.to_string(),
+            },
+            None => Error::InvalidValue {
+                var: self.name.clone(),
+                value: self.value.to_string(),
+                code,
+                message: e.to_string(),
+            },
+        }
+    }
+}
 
-impl<'de> IntoDeserializer<'de, Error> for Val {
+impl<'de> IntoDeserializer<'de, Error> for Val<'de> {
     type Deserializer = Self;
 
     fn into_deserializer(self) -> Self::Deserializer {
@@ -108,46 +722,200 @@ impl<'de> IntoDeserializer<'de, Error> for VarName {
     }
 }
 
-impl<Iter: Iterator<Item = (String, String)>> Iterator for Vars<Iter> {
-    type Item = (VarName, Val);
+impl<'de, Iter: Iterator<Item = (String, Cow<'de, str>)>> Iterator for Vars<Iter> {
+    type Item = (VarName, Val<'de>);
 
     fn next(&mut self) -> Option<Self::Item> {
         self.inner.next().map(|(k, v)| {
-            let var_name = if self.options.keep_names {
-                k.clone()
+            let var_name = self.options.normalize(&k);
+            let v = match self
+                .options
+                .transforms
+                .iter()
+                .find(|(field, _)| field.eq_ignore_ascii_case(&var_name))
+            {
+                Some((_, transform)) => Cow::Owned(transform(&v)),
+                None => v,
+            };
+            let v = if self.options.unquote
+                && !self
+                    .options
+                    .no_unquote
+                    .iter()
+                    .any(|field| field.eq_ignore_ascii_case(&var_name))
+            {
+                unquote(v)
             } else {
-                k.to_lowercase()
+                v
             };
-            (VarName(var_name), Val(k, v))
+            (VarName(var_name), Val::new(k, v, self.options.clone()))
         })
     }
 }
 
+/// Strips a pair of matching single or double quotes surrounding a value
+fn unquote(value: Cow<str>) -> Cow<str> {
+    let quoted = value.len() >= 2
+        && ((value.starts_with('"') && value.ends_with('"'))
+            || (value.starts_with('\'') && value.ends_with('\'')));
+    if !quoted {
+        return value;
+    }
+    match value {
+        Cow::Borrowed(value) => Cow::Borrowed(&value[1..value.len() - 1]),
+        Cow::Owned(value) => Cow::Owned(value[1..value.len() - 1].to_owned()),
+    }
+}
+
+/// Decodes percent encoded characters in the name of a var, keeping names which do not
+/// decode to valid UTF-8
+fn percent_decode(var: String) -> String {
+    let mut bytes = Vec::with_capacity(var.len());
+    let mut rest = var.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let decoded = match tail {
+            [hi, lo, ..] if byte == b'%' && hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() => {
+                std::str::from_utf8(&[*hi, *lo])
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            }
+            _ => None,
+        };
+        match decoded {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).unwrap_or(var)
+}
+
+/// Splits a tuple into at most `len` `:` separated components
+///
+/// Components wrapped in brackets may contain colons, like the IPv6 address of
+/// `[::1]:80`, and are read without their brackets.
+fn split_tuple(
+    value: &str,
+    len: usize,
+) -> Vec<String> {
+    let mut components = Vec::new();
+    let mut rest = value;
+    while components.len() + 1 < len {
+        let trimmed = rest.trim_start();
+        let end = match trimmed.strip_prefix('[').and_then(|r| r.split_once(']')) {
+            Some((inner, tail)) if tail.trim_start().starts_with(':') || tail.trim().is_empty() => {
+                components.push(inner.to_owned());
+                tail.trim_start()
+            }
+            _ => match rest.split_once(':') {
+                Some((component, _)) => {
+                    components.push(component.trim().to_owned());
+                    &rest[component.len()..]
+                }
+                None => break,
+            },
+        };
+        match end.strip_prefix(':') {
+            Some(tail) => rest = tail,
+            None => return components,
+        }
+    }
+    let last = rest.trim();
+    let last = last
+        .strip_prefix('[')
+        .and_then(|last| last.strip_suffix(']'))
+        .unwrap_or(last);
+    components.push(last.to_owned());
+    components
+}
+
+/// Categorizes the failures of parsing values
+trait ParseFailure: fmt::Display {
+    fn code(&self) -> ErrorCode;
+}
+
+impl ParseFailure for ParseIntError {
+    fn code(&self) -> ErrorCode {
+        match self.kind() {
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => ErrorCode::IntegerOutOfRange,
+            _ => ErrorCode::InvalidInteger,
+        }
+    }
+}
+
+impl ParseFailure for ParseFloatError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidFloat
+    }
+}
+
+impl ParseFailure for ParseCharError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidChar
+    }
+}
+
 macro_rules! forward_parsed_values {
     ($($ty:ident => $method:ident,)*) => {
         $(
             fn $method<V>(self, visitor: V) -> Result<V::Value>
-                where V: de::Visitor<'de>
+                where V: serde::de::Visitor<'de>
+            {
+                match self.value.parse::<$ty>() {
+                    // visitors may reject parsed values, i.e. a zero `NonZeroU16`
+                    Ok(val) => val
+                        .into_deserializer()
+                        .$method(visitor)
+                        .map_err(|e| self.context(e)),
+                    // quoted numbers are likely a mistake, so point the quotes out
+                    Err(e) if unquote(self.value.clone()).parse::<$ty>().is_ok() => {
+                        Err(self.invalid(e.code(), format_args!(
+                            "{} (the number is quoted, remove the quotes or enable unquoting)",
+                            e
+                        )))
+                    }
+                    Err(e) => Err(self.invalid(e.code(), e)),
+                }
+            }
+        )*
+    }
+}
+
+macro_rules! forward_parsed_nodes {
+    ($($method:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+                where V: serde::de::Visitor<'de>
             {
-                match self.1.parse::<$ty>() {
-                    Ok(val) => val.into_deserializer().$method(visitor),
-                    Err(e) => Err(de::Error::custom(format_args!("{} while parsing value '{}' provided by {}", e, self.1, self.0)))
+                match self {
+                    Node::Leaf(val) => val.audit(|val| val.$method(visitor)),
+                    node => node.deserialize_any(visitor),
                 }
             }
         )*
     }
 }
 
-impl<'de> de::Deserializer<'de> for Val {
+impl<'de> serde::de::Deserializer<'de> for Val<'de> {
     type Error = Error;
     fn deserialize_any<V>(
         self,
         visitor: V,
     ) -> Result<V::Value>
     where
-        V: de::Visitor<'de>,
+        V: serde::de::Visitor<'de>,
     {
-        self.1.into_deserializer().deserialize_any(visitor)
+        // borrowed values are visited as such, allowing types to borrow them
+        match &self.value {
+            Cow::Borrowed(value) => BorrowedStrDeserializer::new(value).deserialize_any(visitor),
+            Cow::Owned(value) => value.clone().into_deserializer().deserialize_any(visitor),
+        }
+        .map_err(|e| self.context(e))
     }
 
     fn deserialize_seq<V>(
@@ -155,35 +923,123 @@ impl<'de> de::Deserializer<'de> for Val {
         visitor: V,
     ) -> Result<V::Value>
     where
-        V: de::Visitor<'de>,
+        V: serde::de::Visitor<'de>,
     {
-        // std::str::split doesn't work as expected for our use case: when we
-        // get an empty string we want to produce an empty Vec, but split would
-        // still yield an iterator with an empty string in it. So we need to
-        // special case empty strings.
-        if self.1.is_empty() {
+        #[cfg(feature = "json")]
+        if self.options.json_arrays && self.value.trim_start().starts_with('[') {
+            return serde_json::from_str::<serde_json::Value>(&self.value)
+                .and_then(|value| value.deserialize_seq(visitor))
+                .map_err(|e| self.error(e));
+        }
+        if self.options.csv {
+            let values = self
+                .split_csv()?
+                .into_iter()
+                .enumerate()
+                .map(|(index, v)| self.component(Some(index), v));
+            SeqDeserializer::new(values).deserialize_seq(visitor)
+        } else if self.value.is_empty() {
+            // std::str::split doesn't work as expected for our use case: when we
+            // get an empty string we want to produce an empty Vec, but split would
+            // still yield an iterator with an empty string in it. So we need to
+            // special case empty strings.
             SeqDeserializer::new(empty::<Val>()).deserialize_seq(visitor)
         } else {
             let values = self
-                .1
-                .split(',')
-                .map(|v| Val(self.0.clone(), v.trim().to_owned()));
+                .split_escaped(self.separator()?)
+                .into_iter()
+                .enumerate()
+                .map(|(index, v)| self.component(Some(index), v.trim().to_owned()));
             SeqDeserializer::new(values).deserialize_seq(visitor)
         }
     }
 
+    fn deserialize_map<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        // maps are read from separated `key:value` entries, like `a:1,b:2`, where keys
+        // and values are both typed values of this var, i.e. `u32` keys of `0:a,1:b`
+        if self.value.is_empty() {
+            return visitor.visit_map(MapDeserializer::new(empty::<(Val, Val)>()));
+        }
+        let entries = self
+            .split_escaped(self.options.separator.unwrap_or(','))
+            .into_iter()
+            .map(|entry| match entry.split_once(':') {
+                Some((key, value)) => Ok((
+                    self.component(None, key.trim().to_owned()),
+                    self.component(None, value.trim().to_owned()),
+                )),
+                None => Err(self.error(format_args!(
+                    "expected a key:value entry but found '{}'",
+                    entry.trim()
+                ))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        visitor.visit_map(MapDeserializer::new(entries.into_iter()))
+    }
+
+    fn deserialize_tuple<V>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        // tuples are read from `:` separated components, allowing sequences of
+        // tuples like `a:3,b:1`
+        let values = split_tuple(&self.value, len)
+            .into_iter()
+            .map(|v| self.component(self.index, v));
+        SeqDeserializer::new(values)
+            .deserialize_seq(visitor)
+            .map_err(|e| self.context(e))
+    }
+
     fn deserialize_option<V>(
         self,
         visitor: V,
     ) -> Result<V::Value>
     where
-        V: de::Visitor<'de>,
+        V: serde::de::Visitor<'de>,
+    {
+        if self.options.global_null.as_deref() == Some(&*self.value) {
+            return visitor.visit_none();
+        }
+        let log = match &self.options.discarded {
+            Some(log) => log.clone(),
+            None => return visitor.visit_some(self),
+        };
+        let var = self.name.clone();
+        visitor.visit_some(self).inspect_err(|error| {
+            if let Ok(mut log) = log.lock() {
+                log.push(Warning::Discarded {
+                    var,
+                    message: error.to_string(),
+                });
+            }
+        })
+    }
+
+    fn deserialize_bool<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
     {
-        visitor.visit_some(self)
+        match self.options.parse_bool(&self.value) {
+            Ok(val) => visitor.visit_bool(val),
+            Err(e) => Err(self.invalid(ErrorCode::InvalidBool, e)),
+        }
     }
 
     forward_parsed_values! {
-        bool => deserialize_bool,
         u8 => deserialize_u8,
         u16 => deserialize_u16,
         u32 => deserialize_u32,
@@ -192,50 +1048,84 @@ impl<'de> de::Deserializer<'de> for Val {
         i16 => deserialize_i16,
         i32 => deserialize_i32,
         i64 => deserialize_i64,
+        i128 => deserialize_i128,
+        u128 => deserialize_u128,
         f32 => deserialize_f32,
         f64 => deserialize_f64,
+        char => deserialize_char,
     }
 
     #[inline]
     fn deserialize_newtype_struct<V>(
         self,
-        _: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
+        // fields read with envy::duration take bare numbers in the configured unit
+        if name == duration::MARKER {
+            let duration = duration::parse(&self.value, self.options.duration_unit)
+                .map_err(|e| self.error(e))?;
+            return visitor
+                .visit_string(format!("{}ns", duration.as_nanos()))
+                .map_err(|e| self.context(e));
+        }
         visitor.visit_newtype_struct(self)
     }
 
     fn deserialize_enum<V>(
         self,
         _name: &'static str,
-        _variants: &'static [&'static str],
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        if self.options.case_insensitive_enums {
+            if let Some(variant) = variants
+                .iter()
+                .find(|variant| variant.eq_ignore_ascii_case(&self.value))
+            {
+                return visitor
+                    .visit_enum(variant.into_deserializer())
+                    .map_err(|e| self.context(e));
+            }
+        }
+        let value = self.value.clone();
+        visitor
+            .visit_enum(value.into_deserializer())
+            .map_err(|e| self.context(e))
+    }
+
+    fn deserialize_unit<V>(
+        self,
         visitor: V,
     ) -> Result<V::Value>
     where
-        V: de::Visitor<'de>,
+        V: serde::de::Visitor<'de>,
     {
-        visitor.visit_enum(self.1.into_deserializer())
+        // a var being set is all there is to a unit, making `Option<()>` a presence flag
+        visitor.visit_unit()
     }
 
     serde::forward_to_deserialize_any! {
-        char str string unit
-        bytes byte_buf map unit_struct tuple_struct
-        identifier tuple ignored_any
-        struct
+        str string
+        bytes byte_buf unit_struct tuple_struct
+        identifier ignored_any struct
     }
 }
 
-impl<'de> de::Deserializer<'de> for VarName {
+impl<'de> serde::de::Deserializer<'de> for VarName {
     type Error = Error;
     fn deserialize_any<V>(
         self,
         visitor: V,
     ) -> Result<V::Value>
     where
-        V: de::Visitor<'de>,
+        V: serde::de::Visitor<'de>,
     {
         self.0.into_deserializer().deserialize_any(visitor)
     }
@@ -256,303 +1146,5664 @@ impl<'de> de::Deserializer<'de> for VarName {
         char str string unit seq option
         bytes byte_buf map unit_struct tuple_struct
         identifier tuple ignored_any enum
-        struct bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64
+        struct bool u8 u16 u32 u64 i8 i16 i32 i64 i128 u128 f32 f64
     }
 }
 
-/// A deserializer for env vars
-struct Deserializer<'de, Iter: Iterator<Item = (String, String)>> {
-    inner: MapDeserializer<'de, Vars<Iter>, Error>,
-}
+/// The default separator used to split env var names into nested segments
+pub(crate) const NESTING_SEPARATOR: &str = "__";
 
-impl<'de, Iter: Iterator<Item = (String, String)>> Deserializer<'de, Iter> {
-    fn new(
-        vars: Iter,
-        options: Option<VarsOptions>,
-    ) -> Self {
-        Deserializer {
-            inner: MapDeserializer::new(Vars {
-                inner: vars,
-                options: options.unwrap_or_default(),
-            }),
-        }
-    }
+/// A node in the tree of env vars built when nesting is enabled
+enum Node<'de> {
+    Leaf(Val<'de>),
+    /// A namespace of vars along with the prefix of their names
+    Map(String, Vec<(String, Node<'de>)>),
+    Seq(Vec<Node<'de>>),
+    /// A value filling a field whose vars are absent
+    Default(Content),
 }
 
-impl<'de, Iter: Iterator<Item = (String, String)>> de::Deserializer<'de>
-    for Deserializer<'de, Iter>
-{
-    type Error = Error;
-    fn deserialize_any<V>(
-        self,
-        visitor: V,
-    ) -> Result<V::Value>
-    where
-        V: de::Visitor<'de>,
-    {
-        self.deserialize_map(visitor)
+impl<'de> Node<'de> {
+    /// The options of the vars within this node
+    fn options(&self) -> Option<&VarsOptions> {
+        match self {
+            Node::Leaf(val) => Some(&*val.options),
+            Node::Map(_, children) => children.iter().find_map(|(_, node)| node.options()),
+            Node::Seq(nodes) => nodes.iter().find_map(Node::options),
+            Node::Default(_) => None,
+        }
     }
 
-    fn deserialize_map<V>(
-        self,
-        visitor: V,
-    ) -> Result<V::Value>
-    where
-        V: de::Visitor<'de>,
-    {
-        visitor.visit_map(self.inner)
+    /// The options of the vars within this node, when they were read with unknown vars
+    /// denied
+    fn denying_options(&self) -> Option<&VarsOptions> {
+        self.options().filter(|options| options.deny_unknown)
     }
 
-    serde::forward_to_deserialize_any! {
-        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string unit seq
+    /// Inserts a value at the path described by `segments`.
+    ///
+    /// A name used as the namespace of nested vars takes precedence over
+    /// a plain value of the same name
+    fn insert(
+        children: &mut Vec<(String, Node<'de>)>,
+        segments: &[&str],
+        val: Val<'de>,
+    ) {
+        let (head, rest) = match segments.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+        let position = children.iter().position(|(name, _)| name == head);
+        if rest.is_empty() {
+            if position.is_none() {
+                children.push((head.to_string(), Node::Leaf(val)));
+            }
+            return;
+        }
+        let path = format!("{}{}", head, val.options.nesting_separator());
+        let index = match position {
+            Some(index) => {
+                if !matches!(children[index].1, Node::Map(..)) {
+                    children[index].1 = Node::Map(path, Vec::new());
+                }
+                index
+            }
+            None => {
+                children.push((head.to_string(), Node::Map(path, Vec::new())));
+                children.len() - 1
+            }
+        };
+        if let Node::Map(_, grandchildren) = &mut children[index].1 {
+            Node::insert(grandchildren, rest, val);
+        }
+    }
+
+    /// Collects namespaces named `{name}_{index}` into a sequence named `{name}`, unless
+    /// they are themselves `fields` of the struct being read.
+    ///
+    /// Indices must be contiguous starting from 0, and `{name}` may not also be set.
+    fn group_indexed(
+        children: &mut Vec<(String, Node<'de>)>,
+        path: &str,
+        name: &str,
+        fields: &[&str],
+    ) -> Result<()> {
+        let mut group = BTreeMap::new();
+        for (child, node) in std::mem::take(children) {
+            let index = match &node {
+                Node::Map(..) if !fields.contains(&child.as_str()) => child
+                    .strip_prefix(name)
+                    .and_then(|rest| rest.strip_prefix('_'))
+                    .filter(|index| !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()))
+                    .and_then(|index| index.parse::<usize>().ok()),
+                _ => None,
+            };
+            match index {
+                Some(index) => {
+                    group.insert(index, node);
+                }
+                None => children.push((child, node)),
+            }
+        }
+        if group.is_empty() {
+            return Ok(());
+        }
+        let base = format!("{}{}", path, name);
+        if children.iter().any(|(child, _)| child == name) {
+            return Err(Error::Custom(format!(
+                "{} can not be set along with indexed vars like {}_0",
+                base.to_uppercase(),
+                base.to_uppercase()
+            )));
+        }
+        Node::check_contiguous(&base, &group)?;
+        children.push((name.to_string(), Node::Seq(group.into_values().collect())));
+        Ok(())
+    }
+
+    /// Collects vars named `{name}{separator}{index}{separator}{field}` into a sequence
+    /// named `{name}` of namespaces holding each index's fields.
+    ///
+    /// Indices must be contiguous starting from 0
+    fn group_separated(
+        children: &mut Vec<(String, Node<'de>)>,
+        name: &str,
+        separator: &str,
+    ) -> Result<()> {
+        let prefix = format!("{}{}", name, separator);
+        let mut group: BTreeMap<usize, Vec<(String, Node)>> = BTreeMap::new();
+        for (var, node) in std::mem::take(children) {
+            let member = var
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.split_once(separator))
+                .filter(|(index, field)| {
+                    !field.is_empty() && index.bytes().all(|b| b.is_ascii_digit())
+                })
+                .and_then(|(index, field)| Some((index.parse::<usize>().ok()?, field.to_owned())));
+            match member {
+                Some((index, field)) => group.entry(index).or_default().push((field, node)),
+                None => children.push((var, node)),
+            }
+        }
+        if group.is_empty() {
+            return Ok(());
+        }
+        Node::check_contiguous(name, &group)?;
+        children.retain(|(var, _)| var != name);
+        let members = group
+            .into_iter()
+            .map(|(index, fields)| Node::Map(format!("{}{}{}", prefix, index, separator), fields));
+        children.push((name.to_owned(), Node::Seq(members.collect())));
+        Ok(())
+    }
+
+    fn check_contiguous<T>(
+        base: &str,
+        group: &BTreeMap<usize, T>,
+    ) -> Result<()> {
+        match (0..group.len()).find(|index| !group.contains_key(index)) {
+            Some(missing) => Err(Error::Custom(format!(
+                "indexed vars for {} must be contiguous from 0 but index {} is missing",
+                base.to_uppercase(),
+                missing
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Node<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl<'de> serde::de::Deserializer<'de> for Node<'de> {
+    type Error = Error;
+    fn deserialize_any<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self {
+            Node::Leaf(val) => val.audit(|val| val.deserialize_any(visitor)),
+            Node::Map(path, children) => visitor
+                .visit_map(MapDeserializer::new(
+                    children
+                        .into_iter()
+                        .map(|(name, node)| (VarName(name), node)),
+                ))
+                .map_err(|error| match error {
+                    Error::MissingValue(field) => Error::MissingValue(format!("{}{}", path, field)),
+                    _ => error,
+                }),
+            Node::Seq(nodes) => SeqDeserializer::new(nodes.into_iter()).deserialize_any(visitor),
+            Node::Default(content) => content.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_seq<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self {
+            Node::Leaf(val) => val.audit(|val| val.deserialize_seq(visitor)),
+            node => node.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self {
+            Node::Leaf(val) => val.audit(|val| val.deserialize_tuple(len, visitor)),
+            node => node.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self {
+            Node::Leaf(val) => val.audit(|val| val.deserialize_option(visitor)),
+            Node::Default(content) => content.deserialize_option(visitor),
+            node => visitor.visit_some(node),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self {
+            Node::Leaf(val) => val.audit(|val| val.deserialize_newtype_struct(name, visitor)),
+            Node::Default(content) => content.deserialize_newtype_struct(name, visitor),
+            node => visitor.visit_newtype_struct(node),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self {
+            Node::Leaf(val) => val.audit(|val| val.deserialize_enum(name, variants, visitor)),
+            Node::Map(path, mut children) => {
+                // enums may be read from a var naming their variant alongside vars holding
+                // its data
+                let method = children
+                    .iter()
+                    .find_map(|(_, node)| node.options())
+                    .and_then(|options| {
+                        let method = options.enum_method.as_deref()?;
+                        Some(options.normalize(method))
+                    })
+                    .and_then(|method| children.iter().position(|(name, _)| *name == method));
+                match method.map(|index| children.remove(index)) {
+                    Some((_, Node::Leaf(method))) => {
+                        let variant = variants
+                            .iter()
+                            .find(|variant| variant.eq_ignore_ascii_case(&method.value))
+                            .map_or_else(
+                                || method.value.to_string(),
+                                |variant| variant.to_string(),
+                            );
+                        visitor.visit_enum(MethodEnum {
+                            variant,
+                            path,
+                            siblings: children,
+                            options: method.options,
+                        })
+                    }
+                    Some(method) => {
+                        children.push(method);
+                        Node::Map(path, children).deserialize_any(visitor)
+                    }
+                    None => Node::Map(path, children).deserialize_any(visitor),
+                }
+            }
+            node => node.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self {
+            Node::Leaf(val) => val.audit(|val| val.deserialize_struct(name, fields, visitor)),
+            Node::Map(path, mut children) => {
+                for field in fields {
+                    Node::group_indexed(&mut children, &path, field, fields)?;
+                }
+                if let Some(options) = children.iter().find_map(|(_, node)| node.denying_options())
+                {
+                    if let Some(name) = children
+                        .iter()
+                        .filter(|(name, _)| !fields.contains(&&**name))
+                        .map(|(name, _)| format!("{}{}", path, name))
+                        .find(|name| !options.allowed_unknown.contains(name))
+                    {
+                        return Err(Error::UnknownField(name));
+                    }
+                }
+                // nested structs record the provenance of their own fields
+                let options = children
+                    .iter()
+                    .find_map(|(_, node)| node.options())
+                    .cloned();
+                let (start, sources) = match &options {
+                    Some(options) => (options.recorded(), options.sources(fields, &children, &[])),
+                    None => ((0, 0), Vec::new()),
+                };
+                let value = Node::Map(path.clone(), children).deserialize_any(visitor)?;
+                if let Some(options) = options {
+                    options.record(&path, start, sources);
+                }
+                Ok(value)
+            }
+            node => node.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    forward_parsed_nodes! {
+        deserialize_bool,
+        deserialize_u8,
+        deserialize_u16,
+        deserialize_u32,
+        deserialize_u64,
+        deserialize_i8,
+        deserialize_i16,
+        deserialize_i32,
+        deserialize_i64,
+        deserialize_i128,
+        deserialize_u128,
+        deserialize_f32,
+        deserialize_f64,
+        deserialize_unit,
+        deserialize_map,
+        deserialize_char,
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string
         bytes byte_buf unit_struct tuple_struct
-        identifier tuple ignored_any option newtype_struct enum
-        struct
+        identifier
     }
 }
 
-/// Deserializes a type based on information stored in env variables
-pub fn from_env<T>() -> Result<T>
-where
-    T: de::DeserializeOwned,
-{
-    from_iter(env::vars())
+/// An enum read from nested vars, with one var naming its variant and sibling vars
+/// holding its data
+struct MethodEnum<'de> {
+    variant: String,
+    /// The prefix of the names of the sibling vars
+    path: String,
+    siblings: Vec<(String, Node<'de>)>,
+    options: Rc<VarsOptions>,
 }
 
-/// Deserializes a type based on an iterable of `(String, String)`
-/// representing keys and values
-pub fn from_iter<Iter, T>(iter: Iter) -> Result<T>
-where
-    T: de::DeserializeOwned,
-    Iter: IntoIterator<Item = (String, String)>,
-{
-    T::deserialize(Deserializer::new(iter.into_iter(), None)).map_err(|error| match error {
-        Error::MissingValue(value) => Error::MissingValue(value.to_uppercase()),
-        _ => error,
-    })
+impl<'de> MethodEnum<'de> {
+    /// The sibling var named after the variant, which holds the data of newtype and
+    /// tuple variants
+    fn data(self) -> Result<Node<'de>> {
+        let name = self.options.normalize(&self.variant);
+        match self
+            .siblings
+            .into_iter()
+            .find(|(sibling, _)| *sibling == name)
+        {
+            Some((_, node)) => Ok(node),
+            None => Err(Error::MissingValue(format!("{}{}", self.path, name))),
+        }
+    }
 }
 
-/// A type which filters env vars with a prefix for use as serde field inputs.
-///
-/// These types are created with the [prefixed](fn.prefixed.html) module function.
-pub struct Prefixed<'a>(Cow<'a, str>);
+impl<'de> serde::de::EnumAccess<'de> for MethodEnum<'de> {
+    type Error = Error;
+    type Variant = Self;
 
-impl<'a> Prefixed<'a> {
-    /// Deserializes a type based on prefixed env variables
-    pub fn from_env<T>(&self) -> Result<T>
+    fn variant_seed<S>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self)>
     where
-        T: de::DeserializeOwned,
+        S: serde::de::DeserializeSeed<'de>,
     {
-        self.from_iter(env::vars())
+        let variant = seed.deserialize(StrDeserializer::<Error>::new(&self.variant))?;
+        Ok((variant, self))
     }
+}
 
-    /// Deserializes a type based on prefixed (String, String) tuples
-    pub fn from_iter<Iter, T>(
-        &self,
-        iter: Iter,
-    ) -> Result<T>
+impl<'de> serde::de::VariantAccess<'de> for MethodEnum<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S>(
+        self,
+        seed: S,
+    ) -> Result<S::Value>
     where
-        T: de::DeserializeOwned,
-        Iter: IntoIterator<Item = (String, String)>,
+        S: serde::de::DeserializeSeed<'de>,
     {
-        crate::from_iter(iter.into_iter().filter_map(|(k, v)| {
-            if k.starts_with(self.0.as_ref()) {
-                Some((k.trim_start_matches(self.0.as_ref()).to_owned(), v))
-            } else {
-                None
-            }
-        }))
-        .map_err(|error| match error {
-            Error::MissingValue(value) => Error::MissingValue(
-                format!("{prefix}{value}", prefix = self.0, value = value).to_uppercase(),
-            ),
-            _ => error,
-        })
+        seed.deserialize(self.data()?)
+    }
+
+    fn tuple_variant<V>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        serde::de::Deserializer::deserialize_tuple(self.data()?, len, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let data = Node::Map(self.path, self.siblings);
+        serde::de::Deserializer::deserialize_struct(data, "", fields, visitor)
     }
 }
 
-/// Produces a instance of `Prefixed` for prefixing env variable names
-///
-/// # Example
-///
-/// ```no_run
-/// use serde::Deserialize;
-///
-/// #[derive(Deserialize, Debug)]
-/// struct Config {
-///     foo: u16,
-///     bar: bool,
-///     baz: String,
-///     boom: Option<u64>,
-/// }
+/// A deserializer for env vars
 ///
-/// // all env variables will be expected to be prefixed with APP_
-/// // i.e. APP_FOO, APP_BAR, etc
-/// match envy::prefixed("APP_").from_env::<Config>() {
-///     Ok(config) => println!("{:#?}", config),
-///     Err(error) => eprintln!("{:#?}", error),
-/// }
-/// ```
-pub fn prefixed<'a, C>(prefix: C) -> Prefixed<'a>
+/// Most callers want [from_iter](fn.from_iter.html) or a [Config](struct.Config.html).
+/// This type is exposed for wrapping with other serde tooling, such as
+/// `serde_path_to_error`, and reads vars with the default options.
+pub struct Deserializer<Iter> {
+    vars: Iter,
+    options: Rc<VarsOptions>,
+}
+
+impl<Iter> Deserializer<Iter> {
+    /// Creates a deserializer reading (String, String) tuples
+    ///
+    /// Values may also be borrowed, as (String, &str) tuples, allowing the types
+    /// deserialized to borrow them.
+    pub fn new(vars: Iter) -> Self {
+        Deserializer::with_options(vars, None)
+    }
+
+    fn with_options(
+        vars: Iter,
+        options: Option<VarsOptions>,
+    ) -> Self {
+        Deserializer {
+            vars,
+            options: Rc::new(options.unwrap_or_default()),
+        }
+    }
+}
+
+impl<'de, Iter, S> Deserializer<Iter>
 where
-    C: Into<Cow<'a, str>>,
+    Iter: Iterator<Item = (String, S)>,
+    S: Into<Cow<'de, str>>,
 {
-    Prefixed(prefix.into())
+    /// Collects the named nodes to be visited as map entries
+    fn entries(self) -> Result<Vec<(String, Node<'de>)>> {
+        let vars = Vars {
+            inner: self.vars.map(|(name, value)| (name, value.into())),
+            options: self.options,
+        };
+        if !vars.options.nested {
+            return Ok(vars
+                .map(|(VarName(name), val)| (name, Node::Leaf(val)))
+                .collect());
+        }
+        let mut children = Vec::new();
+        for (VarName(name), val) in vars {
+            let segments = name
+                .split(val.options.nesting_separator())
+                .collect::<Vec<_>>();
+            if let Some(max) = val.options.max_depth.filter(|max| segments.len() > max + 1) {
+                return Err(Error::Custom(format!(
+                    "{} exceeds the maximum nesting depth of {}",
+                    val.name, max
+                )));
+            }
+            Node::insert(&mut children, &segments, val);
+        }
+        Ok(children)
+    }
 }
 
-/// A type which keeps the serde field names.
-///
-/// These types are created with the [keep_names](fn.keep_names.html) module function.
-pub struct KeepNames;
+impl<'de, Iter, S> serde::de::Deserializer<'de> for Deserializer<Iter>
+where
+    Iter: Iterator<Item = (String, S)>,
+    S: Into<Cow<'de, str>>,
+{
+    type Error = Error;
+    fn deserialize_any<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
 
-impl KeepNames {
-    /// Deserializes a type based on prefixed env variables
-    pub fn from_env<T>(&self) -> Result<T>
+    fn deserialize_map<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
     where
-        T: de::DeserializeOwned,
+        V: serde::de::Visitor<'de>,
     {
-        self.from_iter(env::vars())
+        visitor.visit_map(MapDeserializer::new(
+            self.entries()?
+                .into_iter()
+                .map(|(name, node)| (VarName(name), node)),
+        ))
     }
 
-    /// Deserializes a type based on prefixed (String, String) tuples
-    pub fn from_iter<Iter, T>(
-        &self,
-        iter: Iter,
-    ) -> Result<T>
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
     where
-        T: de::DeserializeOwned,
-        Iter: IntoIterator<Item = (String, String)>,
+        V: serde::de::Visitor<'de>,
     {
-        let options = VarsOptions { keep_names: true };
-        T::deserialize(Deserializer::new(iter.into_iter(), Some(options)))
+        let options = self.options.clone();
+        let resolver = options.resolver.clone();
+        let mut entries = self.entries()?;
+        if let Some(separator) = &options.group_separator {
+            for field in fields {
+                Node::group_separated(&mut entries, &options.normalize(field), separator)?;
+            }
+        }
+        // indexed namespaces are grouped only for fields reading them as a sequence
+        if options.nested {
+            for field in fields {
+                Node::group_indexed(&mut entries, "", &options.normalize(field), fields)?;
+            }
+        }
+        let mut warnings = Vec::new();
+        for (deprecated, field) in &options.deprecated {
+            let deprecated = options.normalize(deprecated);
+            let field = options.normalize(field);
+            if !entries.iter().any(|(name, _)| *name == deprecated) {
+                continue;
+            }
+            if entries.iter().any(|(name, _)| *name == field) {
+                entries.retain(|(name, _)| *name != deprecated);
+            } else if let Some((name, _)) = entries.iter_mut().find(|(name, _)| *name == deprecated)
+            {
+                *name = field.clone();
+            }
+            warnings.push(Warning::DeprecatedVar {
+                var: deprecated.to_uppercase(),
+                replacement: field.to_uppercase(),
+            });
+        }
+        if options.case_insensitive {
+            for (name, _) in entries.iter_mut() {
+                if let Some(field) = fields.iter().find(|f| f.eq_ignore_ascii_case(name)) {
+                    *name = field.to_string();
+                }
+            }
+        }
+        if let Some(remaining) = options
+            .remaining
+            .as_deref()
+            .map(|field| options.normalize(field))
+            .filter(|field| fields.contains(&field.as_str()))
+        {
+            let (known, rest) = std::mem::take(&mut entries)
+                .into_iter()
+                .partition(|(name, _)| name != &remaining && fields.contains(&name.as_str()));
+            entries = known;
+            entries.push((remaining, Node::Map(String::new(), rest)));
+        }
+        if options.deny_unknown {
+            if let Some((name, _)) = entries.iter().find(|(name, _)| {
+                !fields.contains(&&**name) && !options.allowed_unknown.contains(name)
+            }) {
+                return Err(Error::UnknownField(name.clone()));
+            }
+        }
+        let mut resolved = Vec::new();
+        if let Some(resolver) = resolver {
+            for field in fields {
+                if entries.iter().any(|(name, _)| name == field) {
+                    continue;
+                }
+                if let Some(value) = resolver(field) {
+                    let val = Val::new(field.to_uppercase(), Cow::Owned(value), options.clone());
+                    entries.push((field.to_string(), Node::Leaf(val)));
+                    resolved.push(*field);
+                }
+            }
+        }
+        if let Some(defaults) = &options.defaults {
+            defaults.fill(&mut entries, &defaults.content, "", &options);
+        }
+        let sources = options.sources(fields, &entries, &resolved);
+        let mut filled = Vec::new();
+        for field in fields {
+            if options
+                .soft_missing
+                .iter()
+                .any(|soft| options.normalize(soft) == *field)
+                && !entries.iter().any(|(name, _)| name == field)
+            {
+                let val = Val::new(field.to_uppercase(), Cow::Borrowed(""), options.clone());
+                entries.push((field.to_string(), Node::Leaf(val)));
+                filled.push(*field);
+            }
+        }
+        let start = options.recorded();
+        let value = visitor.visit_map(MapDeserializer::new(
+            entries
+                .into_iter()
+                .map(|(name, node)| (VarName(name), node)),
+        ))?;
+        if let Some(Ok(mut log)) = options.warnings.as_ref().map(|log| log.lock()) {
+            log.extend(warnings);
+        }
+        options.record("", start, sources);
+        if let Some(Ok(mut log)) = options.filled.as_ref().map(|log| log.lock()) {
+            log.extend(filled);
+        }
+        Ok(value)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        // newtype wrappers are read from the same vars as the type they wrap
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool u8 u16 u32 u64 i8 i16 i32 i64 i128 u128 f32 f64 char str string unit seq
+        bytes byte_buf unit_struct tuple_struct
+        identifier tuple ignored_any option enum
+    }
+}
+
+/// Deserializes a type based on information stored in env variables
+pub fn from_env<T>() -> Result<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    from_iter(env::vars())
+}
+
+/// Deserializes a type based on information stored in env variables, skipping vars which
+/// are not valid UTF-8
+///
+/// See [`Config::from_os_env`](struct.Config.html#method.from_os_env) for details.
+pub fn from_os_env<T>() -> Result<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    Config::default().from_os_env()
+}
+
+/// Adapts OS strings into UTF-8 vars, either converting invalid ones lossily or skipping
+/// them
+fn utf8_vars<I>(
+    vars: I,
+    lossy: bool,
+) -> impl Iterator<Item = (String, String)>
+where
+    I: IntoIterator<Item = (OsString, OsString)>,
+{
+    vars.into_iter().filter_map(move |(name, value)| {
+        if lossy {
+            Some((
+                name.to_string_lossy().into_owned(),
+                value.to_string_lossy().into_owned(),
+            ))
+        } else {
+            Some((name.into_string().ok()?, value.into_string().ok()?))
+        }
+    })
+}
+
+/// Describes the env vars a type is deserialized from, one line per var
+///
+/// This is a debugging aid which reads no env vars. See
+/// [Config::explain](struct.Config.html#method.explain) to describe vars read with a prefix
+/// or nesting.
+pub fn explain<T>() -> String
+where
+    T: serde::de::DeserializeOwned,
+{
+    Config::default().explain::<T>()
+}
+
+/// Lists the env vars a type is deserialized from and whether each is optional
+///
+/// No env vars are read. See [Config::field_specs](struct.Config.html#method.field_specs)
+/// for details.
+pub fn field_specs<T>() -> Vec<FieldSpec>
+where
+    T: serde::de::DeserializeOwned,
+{
+    Config::default().field_specs::<T>()
+}
+
+/// Lists the required env vars of a type which a dotenv style example file does not mention
+///
+/// See [Config::check_against_example](struct.Config.html#method.check_against_example)
+/// for details.
+pub fn check_against_example<T, P>(path: P) -> Result<Vec<String>>
+where
+    T: serde::de::DeserializeOwned,
+    P: AsRef<Path>,
+{
+    Config::default().check_against_example::<T, P>(path)
+}
+
+/// Deserializes a type based on an iterable of `(String, String)`
+/// representing keys and values
+pub fn from_iter<Iter, T>(iter: Iter) -> Result<T>
+where
+    T: serde::de::DeserializeOwned,
+    Iter: IntoIterator<Item = (String, String)>,
+{
+    Config::default().from_iter(iter)
+}
+
+/// Checks that env variables deserialize into a type, without returning it
+///
+/// See [Config::validate](struct.Config.html#method.validate) for details.
+pub fn validate<T>() -> Result<()>
+where
+    T: serde::de::DeserializeOwned,
+{
+    Config::default().validate::<T>()
+}
+
+/// Checks that an iterable of `(String, String)` deserializes into a type, without
+/// returning it
+pub fn validate_iter<Iter, T>(iter: Iter) -> Result<()>
+where
+    T: serde::de::DeserializeOwned,
+    Iter: IntoIterator<Item = (String, String)>,
+{
+    Config::default().validate_iter::<Iter, T>(iter)
+}
+
+/// Deserializes a type based on borrowed (&str, &str) tuples, allowing the type to borrow
+/// their values
+///
+/// # Example
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Config<'a> {
+///     host: &'a str,
+/// }
+///
+/// let input = String::from("HOST=localhost");
+/// let vars = input.lines().filter_map(|line| line.split_once('='));
+/// let config = envy::from_iter_borrowed::<_, Config>(vars).unwrap();
+/// assert_eq!(config.host, "localhost");
+/// ```
+pub fn from_iter_borrowed<'de, Iter, T>(iter: Iter) -> Result<T>
+where
+    T: serde::de::Deserialize<'de>,
+    Iter: IntoIterator<Item = (&'de str, &'de str)>,
+{
+    Config::default().from_iter_borrowed(iter)
+}
+
+/// Deserializes a type based on information stored in env variables, with values from
+/// `overrides` taking precedence
+///
+/// # Example
+///
+/// ```no_run
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Config {
+///     port: u16,
+/// }
+///
+/// // i.e. from a `--set port=8080` command line argument
+/// let overrides = vec![(String::from("port"), String::from("8080"))];
+/// match envy::from_env_with_overrides::<_, Config>(overrides) {
+///     Ok(config) => println!("{:#?}", config),
+///     Err(error) => eprintln!("{:#?}", error),
+/// }
+/// ```
+pub fn from_env_with_overrides<O, T>(overrides: O) -> Result<T>
+where
+    T: serde::de::DeserializeOwned,
+    O: IntoIterator<Item = (String, String)>,
+{
+    Config::default().from_env_with_overrides(overrides)
+}
+
+/// Deserializes a type based on the `KEY=value` pairs of a single dotenv file
+///
+/// The process environment is not read. Blank lines and `#` comments are ignored, a leading
+/// `export` is allowed and surrounding quotes are stripped from values, so
+/// `NAME="quoted value"` provides `quoted value`.
+///
+/// # Example
+///
+/// ```no_run
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Config {
+///     database_url: String,
+/// }
+///
+/// match envy::from_file::<_, Config>(".env") {
+///     Ok(config) => println!("{:#?}", config),
+///     Err(error) => eprintln!("{:#?}", error),
+/// }
+/// ```
+pub fn from_file<P, T>(path: P) -> Result<T>
+where
+    T: serde::de::DeserializeOwned,
+    P: AsRef<Path>,
+{
+    Config::default().from_file(path)
+}
+
+/// Deserializes a type based on the `KEY=value` pairs of dotenv files
+///
+/// Values in later files override those in earlier files. See
+/// [`Config::from_dotenv_files`](struct.Config.html#method.from_dotenv_files) to skip missing
+/// files or layer the process environment on top.
+///
+/// # Example
+///
+/// ```no_run
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Config {
+///     database_url: String,
+/// }
+///
+/// match envy::from_dotenv_files::<Config, _>(&[".env", ".env.local"]) {
+///     Ok(config) => println!("{:#?}", config),
+///     Err(error) => eprintln!("{:#?}", error),
+/// }
+/// ```
+pub fn from_dotenv_files<T, P>(paths: &[P]) -> Result<T>
+where
+    T: serde::de::DeserializeOwned,
+    P: AsRef<Path>,
+{
+    Config::default().from_dotenv_files(paths)
+}
+
+/// Deserializes a type based on information stored in env variables, recording
+/// an audit log of the values seen for each field
+///
+/// Use [`Config::sensitive`](struct.Config.html#method.sensitive) to redact secret values
+/// from the log.
+pub fn from_env_audited<T>() -> Result<(T, Vec<AuditEntry>)>
+where
+    T: serde::de::DeserializeOwned,
+{
+    Config::default().from_env_audited()
+}
+
+/// Deserializes a type based on information stored in env variables, reporting where the
+/// value of each field came from
+///
+/// See [`Config::from_env_with_provenance`](struct.Config.html#method.from_env_with_provenance)
+/// for details.
+pub fn from_env_with_provenance<T>() -> Result<(T, HashMap<String, Provenance>)>
+where
+    T: serde::de::DeserializeOwned,
+{
+    Config::default().from_env_with_provenance()
+}
+
+/// Deserializes a type based on information stored in env variables, collecting
+/// warnings about the config
+///
+/// See [`Config::from_env_with_warnings`](struct.Config.html#method.from_env_with_warnings)
+/// for details.
+pub fn from_env_with_warnings<T>() -> Result<(T, Vec<Warning>)>
+where
+    T: serde::de::DeserializeOwned,
+{
+    Config::default().from_env_with_warnings()
+}
+
+/// Deserializes a type based on information stored in env variables, then writes its
+/// effective `KEY=value` representation to a file
+///
+/// Use [`Config::from_env_and_dump`](struct.Config.html#method.from_env_and_dump) to redact
+/// sensitive values.
+pub fn from_env_and_dump<T, P>(path: P) -> Result<T>
+where
+    T: serde::de::DeserializeOwned + Serialize,
+    P: AsRef<Path>,
+{
+    Config::default().from_env_and_dump(path)
+}
+
+/// Deserializes a type based on information stored in env variables along with a
+/// fingerprint of its effective configuration
+///
+/// See [`Config::from_iter_fingerprinted`](struct.Config.html#method.from_iter_fingerprinted)
+/// for details.
+pub fn from_env_fingerprinted<T>() -> Result<(T, u64)>
+where
+    T: serde::de::DeserializeOwned + Serialize,
+{
+    Config::default().from_env_fingerprinted()
+}
+
+/// Deserializes a type based on information stored in env variables, then serializes it
+/// back into sorted env var pairs
+///
+/// See [`Config::canonicalize`](struct.Config.html#method.canonicalize) for details.
+pub fn canonicalize<T>() -> Result<Vec<(String, String)>>
+where
+    T: serde::de::DeserializeOwned + Serialize,
+{
+    Config::default().canonicalize::<T>()
+}
+
+/// Serializes a type into `(String, String)` pairs of env var names and values
+///
+/// Field names are uppercased, sequences are comma separated and `None` values are omitted.
+/// Maps within a field are written as comma separated `key:value` entries. Commas and
+/// backslashes within sequence elements and map entries are escaped with a backslash, so the
+/// pairs deserialize back into the same value with [from_iter](fn.from_iter.html).
+///
+/// Fields of nested structs are written to `__` separated names like `DATABASE__HOST`,
+/// which are only read back with [`Config::nested`](struct.Config.html#method.nested)
+/// enabled, and values wrapped in [Secret](struct.Secret.html) are written as `***`, so
+/// they do not round trip.
+pub fn to_vec<T>(value: &T) -> Result<Vec<(String, String)>>
+where
+    T: Serialize,
+{
+    let mut pairs = Vec::new();
+    value.serialize(ser::Serializer::new(&mut pairs))?;
+    Ok(pairs)
+}
+
+/// Serializes a type into `(String, String)` pairs of env var names and values, safe for
+/// logging
+///
+/// Values wrapped in [Secret](struct.Secret.html) are written as `***`, and fields marked
+/// `#[serde(skip_serializing)]` are omitted. See
+/// [Config::to_vec_redacted](struct.Config.html#method.to_vec_redacted) to also redact
+/// [sensitive](struct.Config.html#method.sensitive) vars.
+pub fn to_vec_redacted<T>(value: &T) -> Result<Vec<(String, String)>>
+where
+    T: Serialize,
+{
+    Config::default().to_vec_redacted(value)
+}
+
+/// Serializes a type into `export KEY='value'` lines which can be sourced by a shell
+///
+/// Values are single quoted, with single quotes within them written as `'\''`.
+pub fn to_shell<T>(value: &T) -> Result<String>
+where
+    T: Serialize,
+{
+    Ok(to_vec(value)?
+        .into_iter()
+        .map(|(key, value)| format!("export {}='{}'\n", key, value.replace('\'', r"'\''")))
+        .collect())
+}
+
+/// Deserializes a type based on information stored in env variables, never failing
+///
+/// Errors are passed to `warn` and the affected fields fall back to their defaults.
+/// See [`Config::from_env_infallible`](struct.Config.html#method.from_env_infallible) for details.
+pub fn from_env_infallible<T, W>(warn: W) -> T
+where
+    T: serde::de::DeserializeOwned + Serialize + Default,
+    W: FnMut(&Error),
+{
+    Config::default().from_env_infallible(warn)
+}
+
+/// A type which filters env vars with a prefix for use as serde field inputs.
+///
+/// These types are created with the [prefixed](fn.prefixed.html) module function.
+pub struct Prefixed<'a> {
+    prefix: Cow<'a, str>,
+    strict: bool,
+    allowed: Vec<String>,
+    remaining: Option<String>,
+}
+
+impl<'a> Prefixed<'a> {
+    /// Requires the prefix to be followed by `separator`
+    ///
+    /// Matching vars then have both the prefix and the separator stripped, so with
+    /// `prefixed("APP").require_separator('_')` the var `APP_HOST` provides `host` while
+    /// `APPLE_HOST` is ignored. Prefixes which already end with the separator are unchanged.
+    pub fn require_separator(
+        mut self,
+        separator: char,
+    ) -> Self {
+        if !self.prefix.ends_with(separator) {
+            self.prefix = Cow::Owned(format!("{}{}", self.prefix, separator));
+        }
+        self
+    }
+
+    /// Fails with an `UnknownField` error when a prefixed var provides no field
+    ///
+    /// See [Config::deny_unknown](struct.Config.html#method.deny_unknown) for details.
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Tolerates the named prefixed vars in [strict](#method.strict) mode, even though
+    /// they provide no field
+    ///
+    /// See [Config::allow_extra](struct.Config.html#method.allow_extra) for details.
+    pub fn allow_extra<I, S>(
+        mut self,
+        vars: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed.extend(vars.into_iter().map(Into::into));
+        self
+    }
+
+    /// Collects the prefixed vars which provide no other field into the named map field
+    ///
+    /// See [Config::collect_remaining](struct.Config.html#method.collect_remaining) for
+    /// details.
+    pub fn collect_remaining<F>(
+        mut self,
+        field: F,
+    ) -> Self
+    where
+        F: Into<String>,
+    {
+        self.remaining = Some(field.into());
+        self
+    }
+
+    /// Deserializes a type based on prefixed env variables
+    pub fn from_env<T>(&self) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.from_iter(env::vars())
+    }
+
+    /// Deserializes a type based on prefixed (String, String) tuples
+    pub fn from_iter<Iter, T>(
+        &self,
+        iter: Iter,
+    ) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        let mut config = Config::new()
+            .prefix(self.prefix.as_ref())
+            .allow_extra(self.allowed.iter().cloned());
+        if let Some(field) = &self.remaining {
+            config = config.collect_remaining(field.as_str());
+        }
+        if self.strict {
+            config.deny_unknown().from_iter(iter)
+        } else {
+            config.from_iter(iter)
+        }
+    }
+}
+
+/// A type which filters env vars with any of several prefixes, in order of precedence, for
+/// use as serde field inputs.
+///
+/// These types are created with the [prefixed_any](fn.prefixed_any.html) module function.
+pub struct PrefixedAny(Vec<String>);
+
+impl PrefixedAny {
+    /// Deserializes a type based on prefixed env variables
+    pub fn from_env<T>(&self) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.from_iter(env::vars())
+    }
+
+    /// Deserializes a type based on prefixed (String, String) tuples
+    pub fn from_iter<Iter, T>(
+        &self,
+        iter: Iter,
+    ) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        // each var is renamed to use the preferred prefix, so errors name the var to set
+        let preferred = self.0.first().map(String::as_str).unwrap_or_default();
+        let mut vars: BTreeMap<String, (usize, String, String)> = BTreeMap::new();
+        for (name, value) in iter {
+            let matched = self.0.iter().enumerate().find_map(|(priority, prefix)| {
+                name.strip_prefix(prefix.as_str())
+                    .map(|stripped| (priority, stripped))
+            });
+            if let Some((priority, stripped)) = matched {
+                let key = stripped.to_lowercase();
+                // `Option::is_none_or` would need rust 1.82
+                #[allow(clippy::unnecessary_map_or)]
+                let preferred_over_taken =
+                    vars.get(&key).map_or(true, |(taken, ..)| priority < *taken);
+                if preferred_over_taken {
+                    let renamed = format!("{}{}", preferred, stripped);
+                    vars.insert(key, (priority, renamed, value));
+                }
+            }
+        }
+        Config::new()
+            .prefix(preferred)
+            .from_iter(vars.into_values().map(|(_, name, value)| (name, value)))
+    }
+}
+
+/// A type which filters env vars with a suffix for use as serde field inputs.
+///
+/// These types are created with the [suffixed](fn.suffixed.html) module function.
+pub struct Suffixed<'a>(Cow<'a, str>);
+
+impl<'a> Suffixed<'a> {
+    /// Deserializes a type based on suffixed env variables
+    pub fn from_env<T>(&self) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.from_iter(env::vars())
+    }
+
+    /// Deserializes a type based on suffixed (String, String) tuples
+    pub fn from_iter<Iter, T>(
+        &self,
+        iter: Iter,
+    ) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        Config::new().suffix(self.0.as_ref()).from_iter(iter)
+    }
+}
+
+/// Produces a instance of `Prefixed` for prefixing env variable names
+///
+/// # Example
+///
+/// ```no_run
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Config {
+///     foo: u16,
+///     bar: bool,
+///     baz: String,
+///     boom: Option<u64>,
+/// }
+///
+/// // all env variables will be expected to be prefixed with APP_
+/// // i.e. APP_FOO, APP_BAR, etc
+/// match envy::prefixed("APP_").from_env::<Config>() {
+///     Ok(config) => println!("{:#?}", config),
+///     Err(error) => eprintln!("{:#?}", error),
+/// }
+/// ```
+pub fn prefixed<'a, C>(prefix: C) -> Prefixed<'a>
+where
+    C: Into<Cow<'a, str>>,
+{
+    Prefixed {
+        prefix: prefix.into(),
+        strict: false,
+        allowed: Vec::new(),
+        remaining: None,
+    }
+}
+
+/// Produces a instance of `Prefixed` reading a single section of env vars, such as those
+/// of one deployment environment
+///
+/// The section is read from vars prefixed with its uppercased name followed by `_`, so
+/// `section("prod")` reads `PROD_DB_HOST` as `db_host` and ignores `STAGING_DB_HOST`.
+///
+/// # Example
+///
+/// ```no_run
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Config {
+///     db_host: String,
+/// }
+///
+/// let environment = std::env::var("APP_ENV").unwrap_or_else(|_| String::from("dev"));
+/// match envy::section(&environment).from_env::<Config>() {
+///     Ok(config) => println!("{:#?}", config),
+///     Err(error) => eprintln!("{:#?}", error),
+/// }
+/// ```
+pub fn section<S>(name: S) -> Prefixed<'static>
+where
+    S: AsRef<str>,
+{
+    prefixed(name.as_ref().to_uppercase()).require_separator('_')
+}
+
+/// Produces a instance of `PrefixedAny` for env variable names with any of several prefixes
+///
+/// Each field is provided by the var with the first of the prefixes, in order, which is
+/// set. This eases renaming prefixes, so with `prefixed_any(["NEW_", "OLD_"])`, `NEW_HOST`
+/// takes precedence over `OLD_HOST` while fields only set as `OLD_PORT` are still read.
+/// Missing values are reported with the first prefix.
+///
+/// # Example
+///
+/// ```no_run
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Config {
+///     host: String,
+/// }
+///
+/// match envy::prefixed_any(["NEW_", "OLD_"]).from_env::<Config>() {
+///     Ok(config) => println!("{:#?}", config),
+///     Err(error) => eprintln!("{:#?}", error),
+/// }
+/// ```
+pub fn prefixed_any<I, S>(prefixes: I) -> PrefixedAny
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    PrefixedAny(prefixes.into_iter().map(Into::into).collect())
+}
+
+/// Produces a instance of `Suffixed` for suffixing env variable names
+///
+/// # Example
+///
+/// ```no_run
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Urls {
+///     foo: String,
+///     bar: String,
+/// }
+///
+/// // all env variables will be expected to be suffixed with _URL
+/// // i.e. FOO_URL, BAR_URL, etc
+/// match envy::suffixed("_URL").from_env::<Urls>() {
+///     Ok(urls) => println!("{:#?}", urls),
+///     Err(error) => eprintln!("{:#?}", error),
+/// }
+/// ```
+pub fn suffixed<'a, C>(suffix: C) -> Suffixed<'a>
+where
+    C: Into<Cow<'a, str>>,
+{
+    Suffixed(suffix.into())
+}
+
+/// A type which keeps the serde field names.
+///
+/// These types are created with the [keep_names](fn.keep_names.html) module function.
+pub struct KeepNames;
+
+impl KeepNames {
+    /// Deserializes a type based on prefixed env variables
+    pub fn from_env<T>(&self) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.from_iter(env::vars())
+    }
+
+    /// Deserializes a type based on prefixed (String, String) tuples
+    pub fn from_iter<Iter, T>(
+        &self,
+        iter: Iter,
+    ) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        let options = VarsOptions {
+            keep_names: true,
+            ..VarsOptions::default()
+        };
+        T::deserialize(Deserializer::with_options(iter.into_iter(), Some(options)))
+    }
+}
+
+/// Produces a instance of `KeepNames` for keeping the serde field names
+pub fn keep_names() -> KeepNames {
+    KeepNames {}
+}
+
+/// A builder for customizing how env vars are deserialized
+///
+/// # Example
+///
+/// ```no_run
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Config {
+///     database_url: String,
+/// }
+///
+/// let config = envy::Config::new()
+///     .missing_template("Configuration error: {var} is not set (see docs/config.md)");
+/// match config.from_env::<Config>() {
+///     Ok(config) => println!("{:#?}", config),
+///     Err(error) => eprintln!("{}", config.display_error(&error)),
+/// }
+/// ```
+#[derive(Default, Clone)]
+pub struct Config {
+    prefix: Option<String>,
+    suffix: Option<String>,
+    missing_template: Option<String>,
+    skip_missing_files: bool,
+    env_overrides_files: bool,
+    max_vars: Option<usize>,
+    required: Vec<String>,
+    /// Fields required unless the field paired with them is true
+    required_unless: Vec<(String, String)>,
+    blocked: Vec<String>,
+    allowed_extra: Vec<String>,
+    lossy_utf8: bool,
+    fallback: Option<Arc<dyn FallbackSource>>,
+    options: VarsOptions,
+}
+
+impl Config {
+    /// Creates a new `Config` with default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only reads env vars starting with a prefix, which is stripped from their names
+    ///
+    /// The prefix is stripped before any other processing of names, so a prefix may contain
+    /// the separator used for [nesting](#method.nested).
+    pub fn prefix<P>(
+        mut self,
+        prefix: P,
+    ) -> Self
+    where
+        P: Into<String>,
+    {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Sets a suffix which vars must end with to be considered, like `_URL`
+    ///
+    /// The suffix is stripped from names along with any [prefix](#method.prefix). A var
+    /// named exactly like the suffix provides a field with an empty name.
+    pub fn suffix<S>(
+        mut self,
+        suffix: S,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        self.suffix = Some(suffix.into());
+        self
+    }
+
+    /// Skips files which do not exist when reading
+    /// [dotenv files](#method.from_dotenv_files) rather than failing
+    pub fn skip_missing_files(
+        mut self,
+        skip: bool,
+    ) -> Self {
+        self.skip_missing_files = skip;
+        self
+    }
+
+    /// Lets the process environment override values read from
+    /// [dotenv files](#method.from_dotenv_files)
+    pub fn env_overrides_files(
+        mut self,
+        env_overrides: bool,
+    ) -> Self {
+        self.env_overrides_files = env_overrides;
+        self
+    }
+
+    /// Fails when more than `max` env vars are provided
+    ///
+    /// This guards against unbounded memory use when reading from untrusted sources.
+    pub fn max_vars(
+        mut self,
+        max: usize,
+    ) -> Self {
+        self.max_vars = Some(max);
+        self
+    }
+
+    /// Requires vars for the named fields, even when they are optional
+    ///
+    /// This allows environment specific strictness, i.e. requiring an `Option` field in
+    /// production, without changing the type. Fields whose vars are absent and which no
+    /// [resolver](#method.resolve) or [fallback source](#method.fallback_source) provides
+    /// fail with a missing value error. Repeated calls add to the required fields.
+    pub fn require<I, S>(
+        mut self,
+        fields: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.required.extend(fields.into_iter().map(Into::into));
+        self
+    }
+
+    /// Requires a var for `field` unless the var for `condition` is true
+    ///
+    /// This expresses cross field constraints such as a certificate path being required
+    /// unless TLS is disabled, i.e. `required_unless("cert_path", "tls_disabled")`. The
+    /// condition is read like a `bool` field and an absent condition counts as false.
+    pub fn required_unless<F, C>(
+        mut self,
+        field: F,
+        condition: C,
+    ) -> Self
+    where
+        F: Into<String>,
+        C: Into<String>,
+    {
+        self.required_unless.push((field.into(), condition.into()));
+        self
+    }
+
+    /// Fills the named fields with an empty value when their vars are absent, rather than
+    /// failing with a missing value error
+    ///
+    /// This suits fields which are logically optional but not typed as an `Option`, such as
+    /// a `String` defaulted later on. Empty values provide empty strings and sequences. Use
+    /// [from_env_with_missing](#method.from_env_with_missing) to learn which were filled.
+    /// Repeated calls add to the filled fields.
+    pub fn soft_missing<I, S>(
+        mut self,
+        fields: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options
+            .soft_missing
+            .extend(fields.into_iter().map(Into::into));
+        self
+    }
+
+    /// Collects the vars which provide no other field into the named map field
+    ///
+    /// This gathers dynamic sets of vars, so with a `prefix("APP_")` and a
+    /// `HashMap<String, u32>` field named `extra`, `APP_FOO=1` and `APP_BAR=2` provide
+    /// `{"foo": 1, "bar": 2}`. Keys are normalized names and vars providing other fields
+    /// are excluded. Only the fields of the type being deserialized collect vars, not those
+    /// of nested structs.
+    pub fn collect_remaining<F>(
+        mut self,
+        field: F,
+    ) -> Self
+    where
+        F: Into<String>,
+    {
+        self.options.remaining = Some(field.into());
+        self
+    }
+
+    /// Ignores the named env vars
+    ///
+    /// This keeps common vars like `PATH` or `HOME` from colliding with fields. Names are
+    /// normalized like var names, so blocking `HOME` also blocks `home`. Repeated calls add
+    /// to the ignored vars.
+    pub fn block<I, S>(
+        mut self,
+        vars: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.blocked.extend(vars.into_iter().map(Into::into));
+        self
+    }
+
+    /// Reads sequences of structs from groups of vars delimited by `separator` and an index
+    ///
+    /// With a separator of `_`, the vars `PLUGIN_0_NAME`, `PLUGIN_0_CONFIG` and
+    /// `PLUGIN_1_NAME` provide the top level field `plugin: Vec<Plugin>` with two elements.
+    /// Indices must be contiguous starting from 0.
+    pub fn indexed_groups(
+        mut self,
+        separator: &str,
+    ) -> Self {
+        self.options.group_separator = Some(separator.to_owned());
+        self
+    }
+
+    /// Reads a field from a deprecated var when the field's own var is not set
+    ///
+    /// Use of the deprecated var is reported by
+    /// [from_env_with_warnings](#method.from_env_with_warnings). This applies to top level
+    /// fields, which are named without a prefix.
+    pub fn deprecated_var(
+        mut self,
+        var: &str,
+        field: &str,
+    ) -> Self {
+        self.options
+            .deprecated
+            .push((var.to_owned(), field.to_owned()));
+        self
+    }
+
+    /// Sets a template used to display errors for missing values
+    ///
+    /// Occurrences of `{var}` within the template are replaced with the name of the
+    /// missing env var when errors are displayed with [display_error](#method.display_error).
+    /// The errors themselves are still `Error::MissingValue`.
+    pub fn missing_template<M>(
+        mut self,
+        template: M,
+    ) -> Self
+    where
+        M: Into<String>,
+    {
+        self.missing_template = Some(template.into());
+        self
+    }
+
+    /// Displays an error, describing missing values with the
+    /// [missing template](#method.missing_template), if one is set
+    pub fn display_error<'a>(
+        &'a self,
+        error: &'a Error,
+    ) -> ErrorDisplay<'a> {
+        ErrorDisplay::new(error, self.missing_template.as_deref())
+    }
+
+    /// Enables deserializing nested structs from env vars whose names are split by `__`
+    ///
+    /// A field `database` of a struct type with a field `host` is read from `DATABASE__HOST`.
+    ///
+    /// Groups of vars sharing an indexed namespace, like `WORKER_0__NAME` and
+    /// `WORKER_1__NAME`, are collected into a `Vec` field named `worker`, unless the
+    /// struct declares a field like `worker_0` itself. Indices must be contiguous
+    /// starting from 0, and `WORKER` may not be set alongside them.
+    pub fn nested(
+        mut self,
+        nested: bool,
+    ) -> Self {
+        self.options.nested = nested;
+        self
+    }
+
+    /// Sets the separator splitting the names of [nested](#method.nested) vars, which
+    /// defaults to `__`
+    ///
+    /// With a separator of `.`, a field `database` of a struct type with a field `host` is
+    /// read from `DATABASE.HOST`. Names without the separator are read as flat fields.
+    pub fn nesting_separator<S>(
+        mut self,
+        separator: S,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        self.options.nesting_separator = Some(separator.into());
+        self
+    }
+
+    /// Reads enums held in [nested](#method.nested) vars from a var naming their variant
+    ///
+    /// With `enum_method_var("method")`, an `auth` enum field is selected by `AUTH__METHOD`,
+    /// matching variant names ignoring case. The data of struct variants is read from the
+    /// sibling vars of the method var, i.e. `AUTH__USER`, while the data of newtype and tuple
+    /// variants is read from the sibling var named after the variant, i.e. `AUTH__TOKEN`.
+    pub fn enum_method_var<M>(
+        mut self,
+        method: M,
+    ) -> Self
+    where
+        M: Into<String>,
+    {
+        self.options.enum_method = Some(method.into());
+        self
+    }
+
+    /// Limits the nesting depth of [nested](#method.nested) vars
+    ///
+    /// Depth is the number of nesting separators in a var's name, so with a maximum of 1 the
+    /// var `DB__HOST` is accepted while `DB__PRIMARY__HOST` fails. This guards against
+    /// malformed names creating deeply nested structures. Depth is unlimited by default.
+    pub fn max_depth(
+        mut self,
+        max: usize,
+    ) -> Self {
+        self.options.max_depth = Some(max);
+        self
+    }
+
+    /// Fails with an `UnknownField` error when a var provides no field
+    ///
+    /// This catches typos such as `DATABSE_URL`. Only vars matching the
+    /// [prefix](#method.prefix) are considered, so it is best paired with one. Nested
+    /// structs are checked the same way.
+    pub fn deny_unknown(mut self) -> Self {
+        self.options.deny_unknown = true;
+        self
+    }
+
+    /// Tolerates the named vars when [unknown vars are denied](#method.deny_unknown)
+    ///
+    /// This allows gradually adopting strict mode by listing vars which are known to be
+    /// ignored, i.e. `allow_extra(["APP_DEBUG_TRACE"])`. Names include any prefix.
+    pub fn allow_extra<I, S>(
+        mut self,
+        vars: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_extra.extend(vars.into_iter().map(Into::into));
+        self
+    }
+
+    /// Treats `.` and `-` in env var names as `_`
+    ///
+    /// This allows vars like `app.db-host`, as exposed by some orchestrators, to map to a
+    /// field named `app_db_host`.
+    pub fn normalize_separators(
+        mut self,
+        normalize: bool,
+    ) -> Self {
+        self.options.normalize_separators = normalize;
+        self
+    }
+
+    /// Sets a function mapping env var names to the names of the fields they provide
+    ///
+    /// This replaces the default lowercasing of names, i.e. to match a serde `rename_all`
+    /// scheme, and is given names with prefixes and suffixes stripped. Nested names are
+    /// mapped whole, before being split into paths.
+    ///
+    /// ```
+    /// # use serde::Deserialize;
+    /// #[derive(Deserialize)]
+    /// #[serde(rename_all = "kebab-case")]
+    /// struct Settings {
+    ///     log_level: String,
+    /// }
+    ///
+    /// let settings: Settings = envy::Config::new()
+    ///     .key_map(|name| name.to_lowercase().replace('_', "-"))
+    ///     .from_iter([(String::from("LOG_LEVEL"), String::from("debug"))])
+    ///     .unwrap();
+    /// assert_eq!(settings.log_level, "debug");
+    /// ```
+    pub fn key_map<F>(
+        mut self,
+        key_map: F,
+    ) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.options.key_map = Some(Arc::new(key_map));
+        self
+    }
+
+    /// Decodes percent encoded characters in env var names, such as `%2E` for `.`
+    ///
+    /// Some orchestrators encode characters which are not allowed in env var names, so
+    /// `APP%2EDB%2EHOST` is read as `APP.DB.HOST`. Names are decoded before prefixes are
+    /// stripped and names are normalized. Names which do not decode to valid UTF-8 are
+    /// read as is.
+    pub fn percent_decode_keys(
+        mut self,
+        decode: bool,
+    ) -> Self {
+        self.options.percent_decode_keys = decode;
+        self
+    }
+
+    /// Removes the given characters from env var names before prefixes are stripped and
+    /// names are normalized
+    ///
+    /// This cleans up markers added by some systems, so `CFG:DEBUG` is read as `CFGDEBUG`
+    /// and provides the `debug` field with a `CFG` prefix. Characters are removed after
+    /// [percent decoding](#method.percent_decode_keys).
+    pub fn strip_chars_from_keys(
+        mut self,
+        chars: &[char],
+    ) -> Self {
+        self.options.key_strip_chars = chars.to_vec();
+        self
+    }
+
+    /// Matches env var names to struct fields ignoring ASCII case
+    ///
+    /// This is disabled by default on every platform. Enabling it suits Windows, where env var
+    /// names are case-insensitive.
+    pub fn case_insensitive(
+        mut self,
+        case_insensitive: bool,
+    ) -> Self {
+        self.options.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Sets the character separating the elements of sequences, which defaults to `,`
+    ///
+    /// This allows `PATH` style values like `a:b:c`. Empty values still provide empty
+    /// sequences.
+    pub fn separator(
+        mut self,
+        separator: char,
+    ) -> Self {
+        self.options.separator = Some(separator);
+        self
+    }
+
+    /// Detects the character separating the elements of each sequence
+    ///
+    /// Each value is split on whichever of `,`, `;` or `|` it contains, so `a;b` and `a,b`
+    /// both provide two elements. Values containing more than one of these characters are
+    /// ambiguous and fail to parse rather than being split on a guess; escape the
+    /// characters which are part of elements, i.e. `a\;b,c`, or set an explicit
+    /// [separator](#method.separator) instead. Detection takes precedence over a
+    /// separator set explicitly.
+    pub fn auto_separator(mut self) -> Self {
+        self.options.auto_separator = true;
+        self
+    }
+
+    /// Parses sequences as a line of CSV
+    ///
+    /// Elements may be double quoted to include commas, with `""` representing a literal
+    /// quote, so `a,"b,c",""` yields `["a", "b,c", ""]`. Quoted empty elements are kept while
+    /// unquoted empty elements, like the one in `a,,b`, are dropped. Without this option
+    /// values are split on every comma and no elements are dropped.
+    pub fn csv(
+        mut self,
+        csv: bool,
+    ) -> Self {
+        self.options.csv = csv;
+        self
+    }
+
+    /// Parses sequences provided as JSON arrays, like `["a","b"]`
+    ///
+    /// Values which don't start with `[` are still split on commas. This requires the `json`
+    /// feature.
+    #[cfg(feature = "json")]
+    pub fn json_arrays(
+        mut self,
+        json_arrays: bool,
+    ) -> Self {
+        self.options.json_arrays = json_arrays;
+        self
+    }
+
+    /// Rejects values containing ASCII control characters, such as null bytes or newlines
+    ///
+    /// Unexpected control characters may indicate an injection attempt.
+    pub fn reject_control_chars(
+        mut self,
+        reject: bool,
+    ) -> Self {
+        self.options.reject_control_chars = reject;
+        self
+    }
+
+    /// Rejects values longer than `max` bytes before parsing them
+    ///
+    /// This guards against enormous values in untrusted environments. Values are unlimited by
+    /// default.
+    pub fn max_value_len(
+        mut self,
+        max: usize,
+    ) -> Self {
+        self.options.max_value_len = Some(max);
+        self
+    }
+
+    /// Reads empty values of bools as `true`, while other values are parsed as usual
+    ///
+    /// This treats `FEATURE=` like a `--feature` command line flag. Use `#[serde(default)]`
+    /// for the flag to be `false` when its var is absent.
+    pub fn empty_bool_true(
+        mut self,
+        empty_true: bool,
+    ) -> Self {
+        self.options.empty_bool_true = empty_true;
+        self
+    }
+
+    /// Treats vars with empty values as absent
+    ///
+    /// This suits shell scripts which "unset" a var with `export TIMEOUT=`, so that an
+    /// `Option` field is `None` and `#[serde(default)]` applies rather than parsing an
+    /// empty value. Since the var is absent, this takes precedence over
+    /// [empty_bool_true](#method.empty_bool_true) and required fields fail with a missing
+    /// value error. By default empty values are read as is.
+    pub fn empty_as_absent(
+        mut self,
+        absent: bool,
+    ) -> Self {
+        self.options.empty_as_absent = absent;
+        self
+    }
+
+    /// Strips a matching pair of single or double quotes surrounding values
+    ///
+    /// This undoes quoting added by tools which pass values through verbatim, so
+    /// `NAME="demo"` provides `demo`. Mismatched quotes and quotes within values are kept.
+    ///
+    /// When unquoting is disabled, quoted numbers, like `PORT="8080"`, are rejected with an
+    /// error pointing out their quotes.
+    pub fn unquote(
+        mut self,
+        unquote: bool,
+    ) -> Self {
+        self.options.unquote = unquote;
+        self
+    }
+
+    /// Keeps the quotes surrounding the values of the named fields when
+    /// [unquoting](#method.unquote)
+    ///
+    /// This suits values where quotes are data, like passwords.
+    pub fn no_unquote<I, S>(
+        mut self,
+        fields: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.no_unquote = fields.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets aliases which rewrite values to their canonical form before they are parsed
+    ///
+    /// Aliases match whole values, or whole elements of sequences, exactly. This allows
+    /// localized tokens like `oui` to be parsed as `true`.
+    pub fn value_aliases<I, K, V>(
+        mut self,
+        aliases: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.options.value_aliases = aliases
+            .into_iter()
+            .map(|(alias, canonical)| (alias.into(), canonical.into()))
+            .collect();
+        self
+    }
+
+    /// Sets a token which deserializes as `None` for every `Option` field
+    ///
+    /// This lets a var explicitly set an optional value to `None`, i.e. `FIELD=__NULL__`.
+    pub fn global_null<N>(
+        mut self,
+        null: N,
+    ) -> Self
+    where
+        N: Into<String>,
+    {
+        self.options.global_null = Some(null.into());
+        self
+    }
+
+    /// Sets the unit of durations provided as bare numbers, which defaults to seconds
+    ///
+    /// This applies to `std::time::Duration` fields read with
+    /// `#[serde(with = "envy::duration")]`, which accept values like `30s`, `1.5h` or `250ms`.
+    /// Values with a unit suffix are unaffected by this setting.
+    pub fn duration_default_unit(
+        mut self,
+        unit: Unit,
+    ) -> Self {
+        self.options.duration_unit = unit;
+        self
+    }
+
+    /// Matches values to enum variants ignoring ASCII case
+    pub fn case_insensitive_enums(
+        mut self,
+        case_insensitive: bool,
+    ) -> Self {
+        self.options.case_insensitive_enums = case_insensitive;
+        self
+    }
+
+    /// Accepts boolean shorthands when deserializing `bool` fields
+    ///
+    /// When enabled `t`, `true`, `yes`, `on` and `1` are parsed as `true` and `f`, `false`,
+    /// `no`, `off` and `0` are parsed as `false`, ignoring case. By default only `true`
+    /// and `false` are accepted.
+    pub fn pg_bools(
+        mut self,
+        pg_bools: bool,
+    ) -> Self {
+        self.options.pg_bools = pg_bools;
+        self
+    }
+
+    /// Accepts the given tokens as `true` and `false` when deserializing `bool` fields
+    ///
+    /// Tokens are matched ignoring case, i.e. `bool_tokens(["yes", "on"], ["no", "off"])`
+    /// accepts `DEBUG=YES` and `VERBOSE=on`. Other values are parsed as they would be
+    /// without these tokens, so `true` and `false` are still accepted.
+    pub fn bool_tokens<T, F>(
+        mut self,
+        truthy: T,
+        falsy: F,
+    ) -> Self
+    where
+        T: IntoIterator,
+        T::Item: Into<String>,
+        F: IntoIterator,
+        F::Item: Into<String>,
+    {
+        self.options.bool_tokens = Some((
+            truthy.into_iter().map(Into::into).collect(),
+            falsy.into_iter().map(Into::into).collect(),
+        ));
+        self
+    }
+
+    /// Sets the names of env vars whose values are redacted in audit logs
+    ///
+    /// Names include any [prefix](#method.prefix) and [suffix](#method.suffix) and are matched
+    /// ignoring case. Repeated calls add to the sensitive vars.
+    pub fn sensitive<I, S>(
+        mut self,
+        vars: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options
+            .sensitive
+            .extend(vars.into_iter().map(Into::into));
+        self
+    }
+
+    /// Treats values of optional fields which fail to parse as absent
+    ///
+    /// With this enabled, an `Option<u16>` field read from `PORT=abc` is `None` rather than
+    /// an error. Values of required fields still fail to parse. Each discarded value is
+    /// reported as a warning by
+    /// [`from_env_with_warnings`](#method.from_env_with_warnings).
+    pub fn lenient_optional(
+        mut self,
+        lenient: bool,
+    ) -> Self {
+        self.options.lenient_optional = lenient;
+        self
+    }
+
+    /// Sets a function consulted for struct fields absent from the env
+    ///
+    /// The function is given the name of the field and may supply a value for it before a
+    /// missing value error is raised.
+    pub fn resolve<F>(
+        mut self,
+        resolver: F,
+    ) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.options.resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    /// Sets a function rewriting the raw value of a field before it is parsed
+    ///
+    /// This allows reformatting or decrypting the value of a single var. The rewritten
+    /// value is then parsed as usual. Nested fields are named by their path, i.e. `db__port`.
+    pub fn transform_value<F>(
+        mut self,
+        field: &str,
+        transform: F,
+    ) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.options
+            .transforms
+            .push((field.to_owned(), Arc::new(transform)));
+        self
+    }
+
+    /// Sets a function decrypting sealed values, those starting with `marker`, before they
+    /// are parsed
+    ///
+    /// This suits values sealed by tools like SOPS, i.e. `enc:...`. The function is given the
+    /// value following the marker and returns its plaintext, or `None` when it can not be
+    /// decrypted. Such failures name the var without revealing its value. Only the values
+    /// of fields are decrypted and audit logs record them sealed.
+    pub fn decrypt<M, F>(
+        mut self,
+        marker: M,
+        decrypt: F,
+    ) -> Self
+    where
+        M: Into<String>,
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.options.decrypt = Some((marker.into(), Arc::new(decrypt)));
+        self
+    }
+
+    /// Sets a source queried for the values of fields whose vars are absent from the env
+    ///
+    /// The source is given the name of the var, including any prefix, and is only queried
+    /// for absent vars. This suits slow backends holding a few rarely set values. Functions
+    /// set with [resolve](#method.resolve) are consulted first.
+    pub fn fallback_source<S>(
+        mut self,
+        source: S,
+    ) -> Self
+    where
+        S: FallbackSource + 'static,
+    {
+        self.fallback = Some(Arc::new(source));
+        self
+    }
+
+    /// Deserializes a type based on env variables
+    pub fn from_env<T>(&self) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.from_iter(env::vars())
+    }
+
+    /// Deserializes a type based on env variables, tolerating vars which are not valid
+    /// UTF-8
+    ///
+    /// Unlike [from_env](#method.from_env), which panics on such vars, they are skipped
+    /// unless [lossy_utf8](#method.lossy_utf8) is enabled. Valid vars are read the same way.
+    pub fn from_os_env<T>(&self) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.from_iter(utf8_vars(env::vars_os(), self.lossy_utf8))
+    }
+
+    /// Converts vars which are not valid UTF-8 read by [from_os_env](#method.from_os_env),
+    /// replacing invalid sequences with `U+FFFD`, rather than skipping them
+    pub fn lossy_utf8(
+        mut self,
+        lossy: bool,
+    ) -> Self {
+        self.lossy_utf8 = lossy;
+        self
+    }
+
+    /// Deserializes a type based on (String, String) tuples
+    pub fn from_iter<Iter, T>(
+        &self,
+        iter: Iter,
+    ) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        self.deserialize(iter, self.options.clone())
+    }
+
+    /// Checks that env variables deserialize into a type, without returning it
+    ///
+    /// This surfaces errors such as missing or invalid values early, i.e. in a startup
+    /// health check. Values are read exactly as [from_env](#method.from_env) reads them, so
+    /// the `Deserialize` impl of the type still runs, but the value it builds is dropped.
+    pub fn validate<T>(&self) -> Result<()>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.validate_iter::<_, T>(env::vars())
+    }
+
+    /// Checks that (String, String) tuples deserialize into a type, without returning it
+    ///
+    /// See [validate](#method.validate) for details.
+    pub fn validate_iter<Iter, T>(
+        &self,
+        iter: Iter,
+    ) -> Result<()>
+    where
+        T: serde::de::DeserializeOwned,
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        self.from_iter::<Iter, T>(iter).map(drop)
+    }
+
+    /// Deserializes a type based on borrowed (&str, &str) tuples
+    ///
+    /// Unlike [from_iter](#method.from_iter), values are not copied, so the types
+    /// deserialized may borrow them with `&str` or `Cow<str>` fields. Values rewritten
+    /// while reading, such as transformed values or the elements of sequences, are copied
+    /// and only provide owned fields.
+    pub fn from_iter_borrowed<'de, Iter, T>(
+        &self,
+        iter: Iter,
+    ) -> Result<T>
+    where
+        T: serde::de::Deserialize<'de>,
+        Iter: IntoIterator<Item = (&'de str, &'de str)>,
+    {
+        self.deserialize(
+            iter.into_iter().map(|(k, v)| (k.to_owned(), v)),
+            self.options.clone(),
+        )
+    }
+
+    /// Sets a function run on deserialized values before they are returned, allowing
+    /// defaults which depend on other fields
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Config {
+    ///     tls_enabled: bool,
+    ///     tls_verify: Option<bool>,
+    /// }
+    ///
+    /// let config = envy::Config::new()
+    ///     .with_dependent_defaults(|config: &mut Config| {
+    ///         config.tls_verify.get_or_insert(config.tls_enabled);
+    ///     })
+    ///     .from_iter(vec![(String::from("TLS_ENABLED"), String::from("true"))])
+    ///     .unwrap();
+    /// assert_eq!(config.tls_verify, Some(true));
+    /// ```
+    pub fn with_dependent_defaults<T, F>(
+        self,
+        defaults: F,
+    ) -> DependentDefaults<T, F>
+    where
+        F: Fn(&mut T),
+    {
+        DependentDefaults {
+            config: self,
+            defaults,
+            marker: PhantomData,
+        }
+    }
+
+    /// Deserializes a type based on env variables, with values from `overrides` taking
+    /// precedence
+    ///
+    /// This suits tools accepting `--set key=value` style overrides on top of the env. Names
+    /// of overrides are normalized the same way as env var names, so an override named `port`
+    /// replaces the env var `PORT`, or `APP_PORT` with a [prefix](#method.prefix) of `APP_`.
+    pub fn from_env_with_overrides<O, T>(
+        &self,
+        overrides: O,
+    ) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        O: IntoIterator<Item = (String, String)>,
+    {
+        self.from_iter_with_overrides(env::vars(), overrides)
+    }
+
+    /// Deserializes a type based on (String, String) tuples, with values from `overrides`
+    /// taking precedence
+    pub fn from_iter_with_overrides<Iter, O, T>(
+        &self,
+        iter: Iter,
+        overrides: O,
+    ) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        Iter: IntoIterator<Item = (String, String)>,
+        O: IntoIterator<Item = (String, String)>,
+    {
+        let key = |name: &str| {
+            self.options
+                .normalize(self.strip_affixes(name).unwrap_or(name))
+        };
+        // overrides may be named with or without the affixes, which the input must carry
+        let overrides = overrides
+            .into_iter()
+            .map(|(name, value)| {
+                (
+                    self.var_name(self.strip_affixes(&name).unwrap_or(&name)),
+                    value,
+                )
+            })
+            .collect::<Vec<_>>();
+        let overridden = overrides
+            .iter()
+            .map(|(name, _)| key(name))
+            .collect::<HashSet<_>>();
+        self.from_iter(
+            iter.into_iter()
+                .filter(|(name, _)| !overridden.contains(&key(name)))
+                .chain(overrides),
+        )
+    }
+
+    /// Deserializes a type based on the `KEY=value` pairs of a single dotenv file
+    ///
+    /// Unlike [from_dotenv_files](#method.from_dotenv_files), the process environment is
+    /// never read and a missing file is always an error.
+    pub fn from_file<P, T>(
+        &self,
+        path: P,
+    ) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        P: AsRef<Path>,
+    {
+        self.from_iter(dotenv::read(path.as_ref())?)
+    }
+
+    /// Deserializes a type based on the `KEY=value` pairs of dotenv files
+    ///
+    /// Values in later files override those in earlier files. The process environment is
+    /// not read unless [`env_overrides_files`](#method.env_overrides_files) is enabled.
+    pub fn from_dotenv_files<T, P>(
+        &self,
+        paths: &[P],
+    ) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        P: AsRef<Path>,
+    {
+        self.from_iter(
+            self.dotenv_vars(paths)?
+                .into_iter()
+                .map(|(name, (value, _))| (name, value)),
+        )
+    }
+
+    /// Reads the vars of dotenv files along with the path of the file defining each, which
+    /// is `None` for vars of the process environment
+    fn dotenv_vars<'p, P>(
+        &self,
+        paths: &'p [P],
+    ) -> Result<BTreeMap<String, (String, Option<&'p Path>)>>
+    where
+        P: AsRef<Path>,
+    {
+        let mut vars = BTreeMap::new();
+        for path in paths {
+            let path = path.as_ref();
+            if self.skip_missing_files && !path.exists() {
+                continue;
+            }
+            for (name, value) in dotenv::read(path)? {
+                vars.insert(name, (value, Some(path)));
+            }
+        }
+        if self.env_overrides_files {
+            vars.extend(env::vars().map(|(name, value)| (name, (value, None))));
+        }
+        Ok(vars)
+    }
+
+    /// Deserializes a type based on env variables, recording an audit log of the
+    /// values seen for each field
+    pub fn from_env_audited<T>(&self) -> Result<(T, Vec<AuditEntry>)>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.from_iter_audited(env::vars())
+    }
+
+    /// Deserializes a type based on (String, String) tuples, recording an audit log of
+    /// the values seen for each field
+    pub fn from_iter_audited<Iter, T>(
+        &self,
+        iter: Iter,
+    ) -> Result<(T, Vec<AuditEntry>)>
+    where
+        T: serde::de::DeserializeOwned,
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        let (value, entries) = self.audited(iter);
+        Ok((value?, entries))
+    }
+
+    /// Deserializes a type based on env variables, collecting warnings about the config
+    ///
+    /// Warnings report non fatal observations, such as the use of a
+    /// [deprecated var](#method.deprecated_var) or fields left to their defaults.
+    pub fn from_env_with_warnings<T>(&self) -> Result<(T, Vec<Warning>)>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.from_iter_with_warnings(env::vars())
+    }
+
+    /// Deserializes a type based on (String, String) tuples, collecting warnings about the
+    /// config
+    pub fn from_iter_with_warnings<Iter, T>(
+        &self,
+        iter: Iter,
+    ) -> Result<(T, Vec<Warning>)>
+    where
+        T: serde::de::DeserializeOwned,
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let options = VarsOptions {
+            warnings: Some(log.clone()),
+            ..self.options.clone()
+        };
+        let value = self.deserialize(iter, options)?;
+        // `Option` fields have no default, they are simply empty
+        let optional = self
+            .field_specs::<T>()
+            .into_iter()
+            .filter(|spec| spec.optional)
+            .map(|spec| spec.name)
+            .collect::<HashSet<_>>();
+        let warnings = log
+            .lock()
+            .map(|mut log| log.split_off(0))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|warning| match warning {
+                Warning::DeprecatedVar { var, replacement } => Warning::DeprecatedVar {
+                    var: self.var_name(&var),
+                    replacement: self.var_name(&replacement),
+                },
+                Warning::Defaulted { var } => Warning::Defaulted {
+                    var: self.var_name(&var),
+                },
+                Warning::Discarded { var, message } => Warning::Discarded {
+                    var: self.var_name(&var),
+                    message,
+                },
+            })
+            .filter(
+                |warning| !matches!(warning, Warning::Defaulted { var } if optional.contains(var)),
+            )
+            .collect();
+        Ok((value, warnings))
+    }
+
+    /// Deserializes a type based on env variables, reporting where the value of each field
+    /// came from
+    ///
+    /// Provenance is keyed by the path of each field, like `db__host` for the `host` field of
+    /// a nested `db` struct, and reports whether each field was provided by an env var,
+    /// supplied by a fallback or left to its default. The fields of nested structs are
+    /// reported individually, while nested maps are reported as provided by their namespace.
+    pub fn from_env_with_provenance<T>(&self) -> Result<(T, HashMap<String, Provenance>)>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.from_iter_with_provenance(env::vars())
+    }
+
+    /// Deserializes a type based on (String, String) tuples, reporting where the value of
+    /// each field came from
+    pub fn from_iter_with_provenance<Iter, T>(
+        &self,
+        iter: Iter,
+    ) -> Result<(T, HashMap<String, Provenance>)>
+    where
+        T: serde::de::DeserializeOwned,
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let options = VarsOptions {
+            provenance: Some(log.clone()),
+            ..self.options.clone()
+        };
+        let value = self.deserialize(iter, options)?;
+        let provenance = log
+            .lock()
+            .map(|mut log| log.split_off(0))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(field, provenance)| {
+                let provenance = match provenance {
+                    Provenance::Env(var) => Provenance::Env(self.var_name(&var)),
+                    Provenance::Fallback(var) => Provenance::Fallback(self.var_name(&var)),
+                    provenance => provenance,
+                };
+                (field, provenance)
+            })
+            .collect();
+        Ok((value, provenance))
+    }
+
+    /// Deserializes a type based on env variables, listing the
+    /// [soft missing](#method.soft_missing) fields which were absent and filled
+    pub fn from_env_with_missing<T>(&self) -> Result<(T, Vec<&'static str>)>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.from_iter_with_missing(env::vars())
+    }
+
+    /// Deserializes a type based on (String, String) tuples, listing the
+    /// [soft missing](#method.soft_missing) fields which were absent and filled
+    pub fn from_iter_with_missing<Iter, T>(
+        &self,
+        iter: Iter,
+    ) -> Result<(T, Vec<&'static str>)>
+    where
+        T: serde::de::DeserializeOwned,
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let options = VarsOptions {
+            filled: Some(log.clone()),
+            ..self.options.clone()
+        };
+        let value = self.deserialize(iter, options)?;
+        let filled = log
+            .lock()
+            .map(|mut log| log.split_off(0))
+            .unwrap_or_default();
+        Ok((value, filled))
+    }
+
+    /// Deserializes a type based on the `KEY=value` pairs of dotenv files, reporting where
+    /// the value of each field came from
+    ///
+    /// Fields provided by a file are reported with the path of the last file defining
+    /// their var.
+    pub fn from_dotenv_files_with_provenance<T, P>(
+        &self,
+        paths: &[P],
+    ) -> Result<(T, HashMap<String, Provenance>)>
+    where
+        T: serde::de::DeserializeOwned,
+        P: AsRef<Path>,
+    {
+        let vars = self.dotenv_vars(paths)?;
+        let (value, mut provenance) = self.from_iter_with_provenance(
+            vars.iter()
+                .map(|(name, (value, _))| (name.clone(), value.clone())),
+        )?;
+        for source in provenance.values_mut() {
+            let path = match source {
+                Provenance::Env(var) => vars
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(var))
+                    .and_then(|(_, (_, path))| *path),
+                _ => None,
+            };
+            if let (Some(path), Provenance::Env(var)) = (path, &source) {
+                *source = Provenance::File {
+                    var: var.clone(),
+                    path: path.to_owned(),
+                };
+            }
+        }
+        Ok((value, provenance))
+    }
+
+    /// Deserializes a type based on env variables, never failing
+    ///
+    /// Values which fail to parse are discarded and missing fields are filled, one at a time,
+    /// with the value of the field in `T::default()`, keeping every valid value. Nested fields
+    /// are filled individually when [nesting](#method.nested) is enabled.
+    /// Deserialization is retried after each change. If it still fails, `T::default()` is
+    /// returned. Each error encountered along the way is passed to `warn`.
+    pub fn from_env_infallible<T, W>(
+        &self,
+        warn: W,
+    ) -> T
+    where
+        T: serde::de::DeserializeOwned + Serialize + Default,
+        W: FnMut(&Error),
+    {
+        self.from_iter_infallible(env::vars(), warn)
+    }
+
+    /// Deserializes a type based on (String, String) tuples, never failing
+    ///
+    /// See [`Config::from_env_infallible`](#method.from_env_infallible) for details.
+    pub fn from_iter_infallible<Iter, T, W>(
+        &self,
+        iter: Iter,
+        mut warn: W,
+    ) -> T
+    where
+        T: serde::de::DeserializeOwned + Serialize + Default,
+        Iter: IntoIterator<Item = (String, String)>,
+        W: FnMut(&Error),
+    {
+        let mut vars = iter.into_iter().collect::<Vec<_>>();
+        let content = match Content::capture(&T::default()) {
+            Ok(content) => content,
+            Err(_) => return T::default(),
+        };
+        // the fields of the default value, by the name of the var providing each
+        let mut paths = Defaults::paths(&content, "", &self.options)
+            .into_iter()
+            .map(|path| (self.var_name(&path), path))
+            .collect::<Vec<_>>();
+        let mut defaults = Arc::new(Defaults {
+            content,
+            filled: HashSet::new(),
+        });
+        loop {
+            let options = VarsOptions {
+                defaults: Some(defaults.clone()),
+                ..self.options.clone()
+            };
+            let (value, entries) = self.audited_with(vars.clone(), options);
+            let error = match value {
+                Ok(value) => return value,
+                Err(error) => error,
+            };
+            warn(&error);
+            let before = vars.len();
+            vars.retain(|(key, _)| {
+                let var = self
+                    .strip_affixes(&self.decode_key(key.clone()))
+                    .map(|name| self.var_name(name));
+                !entries
+                    .iter()
+                    .any(|entry| !entry.parsed_ok && var.as_ref() == Some(&entry.var))
+            });
+            if vars.len() != before {
+                continue;
+            }
+            let default = match &error {
+                Error::MissingValue(var) => paths.iter().position(|(name, _)| name == var),
+                _ => None,
+            };
+            match (default, Arc::get_mut(&mut defaults)) {
+                (Some(index), Some(defaults)) => {
+                    defaults.filled.insert(paths.remove(index).1);
+                }
+                _ => return T::default(),
+            }
+        }
+    }
+
+    /// Serializes a value into env var pairs, replacing the values of
+    /// [sensitive](#method.sensitive) vars and [Secret](struct.Secret.html) fields with `***`
+    pub fn to_vec_redacted<T>(
+        &self,
+        value: &T,
+    ) -> Result<Vec<(String, String)>>
+    where
+        T: Serialize,
+    {
+        let mut pairs = to_vec(value)?;
+        for (key, value) in pairs.iter_mut() {
+            if self.is_sensitive(&self.var_name(key)) {
+                *value = String::from("***");
+            }
+        }
+        Ok(pairs)
+    }
+
+    /// Deserializes a type based on env variables, then serializes it back into sorted
+    /// env var pairs
+    ///
+    /// This produces a canonical representation for reproducible artifacts: names include
+    /// any prefix and suffix and are uppercased, values take their normalized formats, like
+    /// `true` for `yes` or `80,443` for `80, 443`, and [sensitive](#method.sensitive) values
+    /// are replaced with `***`.
+    pub fn canonicalize<T>(&self) -> Result<Vec<(String, String)>>
+    where
+        T: serde::de::DeserializeOwned + Serialize,
+    {
+        self.canonicalize_iter::<_, T>(env::vars())
+    }
+
+    /// Deserializes a type based on (String, String) tuples, then serializes it back into
+    /// sorted env var pairs
+    pub fn canonicalize_iter<Iter, T>(
+        &self,
+        iter: Iter,
+    ) -> Result<Vec<(String, String)>>
+    where
+        T: serde::de::DeserializeOwned + Serialize,
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        let value: T = self.from_iter(iter)?;
+        let mut pairs = self
+            .to_vec_redacted(&value)?
+            .into_iter()
+            .map(|(key, value)| (self.var_name(&key), value))
+            .collect::<Vec<_>>();
+        pairs.sort();
+        Ok(pairs)
+    }
+
+    /// Deserializes a type based on env variables, then writes its effective
+    /// `KEY=value` representation to a file with sensitive values redacted
+    pub fn from_env_and_dump<T, P>(
+        &self,
+        path: P,
+    ) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned + Serialize,
+        P: AsRef<Path>,
+    {
+        self.from_iter_and_dump(env::vars(), path)
+    }
+
+    /// Deserializes a type based on (String, String) tuples, then writes its effective
+    /// `KEY=value` representation to a file with sensitive values redacted
+    pub fn from_iter_and_dump<Iter, T, P>(
+        &self,
+        iter: Iter,
+        path: P,
+    ) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned + Serialize,
+        Iter: IntoIterator<Item = (String, String)>,
+        P: AsRef<Path>,
+    {
+        let value = self.from_iter(iter)?;
+        let contents = self
+            .to_vec_redacted(&value)?
+            .into_iter()
+            .map(|(key, value)| format!("{}={}\n", key, value))
+            .collect::<String>();
+        fs::write(path.as_ref(), contents).map_err(|e| {
+            Error::Custom(format!(
+                "failed to write {}: {}",
+                path.as_ref().display(),
+                e
+            ))
+        })?;
+        Ok(value)
+    }
+
+    /// Deserializes a type based on env variables along with a fingerprint of its effective
+    /// configuration
+    ///
+    /// See [from_iter_fingerprinted](#method.from_iter_fingerprinted) for details
+    pub fn from_env_fingerprinted<T>(&self) -> Result<(T, u64)>
+    where
+        T: serde::de::DeserializeOwned + Serialize,
+    {
+        self.from_iter_fingerprinted(env::vars())
+    }
+
+    /// Deserializes a type based on (String, String) tuples along with a fingerprint of its
+    /// effective configuration
+    ///
+    /// The fingerprint is a hash of the value's serialized env vars, so it only changes when
+    /// the deserialized value does. It does not depend on the order of vars or map entries
+    /// and is stable across runs, making it suitable for detecting config changes across
+    /// restarts. [Sensitive](#method.sensitive) vars are left out of the fingerprint.
+    pub fn from_iter_fingerprinted<Iter, T>(
+        &self,
+        iter: Iter,
+    ) -> Result<(T, u64)>
+    where
+        T: serde::de::DeserializeOwned + Serialize,
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        let value = self.from_iter(iter)?;
+        let mut pairs = to_vec(&value)?;
+        pairs.retain(|(key, _)| !self.is_sensitive(&self.var_name(key)));
+        pairs.sort();
+        // 64 bit FNV-1a, which unlike std's hashers is guaranteed to be stable
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for (key, value) in &pairs {
+            for byte in key.bytes().chain([0]).chain(value.bytes()).chain([0]) {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        Ok((value, hash))
+    }
+
+    fn audited<Iter, T>(
+        &self,
+        iter: Iter,
+    ) -> (Result<T>, Vec<AuditEntry>)
+    where
+        T: serde::de::DeserializeOwned,
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        self.audited_with(iter, self.options.clone())
+    }
+
+    fn audited_with<Iter, T>(
+        &self,
+        iter: Iter,
+        options: VarsOptions,
+    ) -> (Result<T>, Vec<AuditEntry>)
+    where
+        T: serde::de::DeserializeOwned,
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let options = VarsOptions {
+            audit: Some(log.clone()),
+            ..options
+        };
+        let value = self.deserialize(iter, options);
+        let entries = log
+            .lock()
+            .map(|mut log| log.split_off(0))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|entry| {
+                let var = self.var_name(&entry.var);
+                let value = if self.is_sensitive(&var) {
+                    String::from("***")
+                } else {
+                    entry.value
+                };
+                AuditEntry {
+                    var,
+                    value,
+                    ..entry
+                }
+            })
+            .collect();
+        (value, entries)
+    }
+
+    /// Whether the named var, including any prefix and suffix, is
+    /// [sensitive](#method.sensitive)
+    fn is_sensitive(
+        &self,
+        var: &str,
+    ) -> bool {
+        self.options
+            .sensitive
+            .iter()
+            .any(|s| s.eq_ignore_ascii_case(var))
+    }
+
+    /// Decodes the name of a var when [percent decoding](#method.percent_decode_keys) is
+    /// enabled, then removes any [stripped characters](#method.strip_chars_from_keys)
+    fn decode_key(
+        &self,
+        var: String,
+    ) -> String {
+        let var = if self.options.percent_decode_keys && var.contains('%') {
+            percent_decode(var)
+        } else {
+            var
+        };
+        if self.options.key_strip_chars.is_empty() {
+            var
+        } else {
+            var.replace(self.options.key_strip_chars.as_slice(), "")
+        }
+    }
+
+    /// Strips the prefix and suffix from the name of a var, unless it lacks them
+    fn strip_affixes<'k>(
+        &self,
+        var: &'k str,
+    ) -> Option<&'k str> {
+        var.strip_prefix(self.prefix.as_deref().unwrap_or_default())?
+            .strip_suffix(self.suffix.as_deref().unwrap_or_default())
+    }
+
+    /// Produces the name of the var providing a field
+    fn var_name(
+        &self,
+        field: &str,
+    ) -> String {
+        format!(
+            "{}{}{}",
+            self.prefix.as_deref().unwrap_or_default(),
+            field,
+            self.suffix.as_deref().unwrap_or_default()
+        )
+        .to_uppercase()
+    }
+
+    /// Deserializes a type, retrying without the vars of optional fields whose values
+    /// fail to parse
+    fn deserialize_lenient<'de, Iter, S, T>(
+        &self,
+        iter: Iter,
+        options: VarsOptions,
+    ) -> Result<T>
+    where
+        T: serde::de::Deserialize<'de>,
+        Iter: IntoIterator<Item = (String, S)>,
+        S: Into<Cow<'de, str>>,
+    {
+        let mut vars = iter
+            .into_iter()
+            .map(|(k, v)| (k, v.into()))
+            .collect::<Vec<(String, Cow<str>)>>();
+        let mut discarded = Vec::new();
+        loop {
+            let log = Arc::new(Mutex::new(Vec::new()));
+            let attempt = VarsOptions {
+                discarded: Some(log.clone()),
+                ..options.clone()
+            };
+            let error = match self.deserialize(vars.clone(), attempt) {
+                Ok(value) => {
+                    if let Some(Ok(mut log)) = options.warnings.as_ref().map(|log| log.lock()) {
+                        log.extend(discarded);
+                    }
+                    return Ok(value);
+                }
+                Err(error) => error,
+            };
+            let warnings = log
+                .lock()
+                .map(|mut log| log.split_off(0))
+                .unwrap_or_default();
+            let before = vars.len();
+            vars.retain(|(key, _)| {
+                !warnings.iter().any(|warning| match warning {
+                    Warning::Discarded { var, .. } => {
+                        self.strip_affixes(&self.decode_key(key.clone())) == Some(var)
+                    }
+                    _ => false,
+                })
+            });
+            if vars.len() == before {
+                return Err(error);
+            }
+            discarded.extend(warnings);
+        }
+    }
+
+    fn deserialize<'de, Iter, S, T>(
+        &self,
+        iter: Iter,
+        options: VarsOptions,
+    ) -> Result<T>
+    where
+        T: serde::de::Deserialize<'de>,
+        Iter: IntoIterator<Item = (String, S)>,
+        S: Into<Cow<'de, str>>,
+    {
+        if self.options.lenient_optional && options.discarded.is_none() {
+            return self.deserialize_lenient(iter, options);
+        }
+        let options = VarsOptions {
+            allowed_unknown: self
+                .allowed_extra
+                .iter()
+                .map(|var| {
+                    self.options
+                        .normalize(self.strip_affixes(var).unwrap_or(var))
+                })
+                .collect(),
+            ..options
+        };
+        // reading stops one var past the limit so exceeding it can be detected
+        // without buffering an unbounded number of vars
+        let count = Cell::new(0);
+        let seen = RefCell::new(HashMap::new());
+        let vars = iter
+            .into_iter()
+            .take(
+                self.max_vars
+                    .map_or(usize::MAX, |max| max.saturating_add(1)),
+            )
+            .inspect(|_| count.set(count.get() + 1))
+            .map(|(k, v)| (k, v.into()))
+            .filter(|(_, v): &(String, Cow<str>)| !(self.options.empty_as_absent && v.is_empty()))
+            .map(|(k, v)| (self.decode_key(k), v))
+            .filter_map(|(k, v)| self.strip_affixes(&k).map(|k| (k.to_owned(), v)))
+            .filter(|(k, _)| {
+                let name = self.options.normalize(k);
+                !self
+                    .blocked
+                    .iter()
+                    .any(|blocked| self.options.normalize(blocked) == name)
+            })
+            .inspect(|(k, v)| {
+                if !self.required.is_empty() || !self.required_unless.is_empty() {
+                    seen.borrow_mut()
+                        .insert(self.options.normalize(k), v.to_string());
+                }
+            });
+        let options = match &self.fallback {
+            Some(fallback) => {
+                let resolver = options.resolver.clone();
+                let fallback = fallback.clone();
+                let config = Config {
+                    prefix: self.prefix.clone(),
+                    suffix: self.suffix.clone(),
+                    ..Config::default()
+                };
+                VarsOptions {
+                    resolver: Some(Arc::new(move |field: &str| {
+                        resolver
+                            .as_ref()
+                            .and_then(|resolver| resolver(field))
+                            .or_else(|| fallback.get(&config.var_name(field)))
+                    })),
+                    ..options
+                }
+            }
+            None => options,
+        };
+        // values supplied for absent vars count towards required fields
+        let resolved = Arc::new(Mutex::new(Vec::new()));
+        let options = match &options.resolver {
+            Some(resolver) if !self.required.is_empty() || !self.required_unless.is_empty() => {
+                let resolver = resolver.clone();
+                let log = resolved.clone();
+                VarsOptions {
+                    resolver: Some(Arc::new(move |field: &str| {
+                        let value = resolver(field);
+                        if let (Some(value), Ok(mut log)) = (&value, log.lock()) {
+                            log.push((field.to_owned(), value.clone()));
+                        }
+                        value
+                    })),
+                    ..options
+                }
+            }
+            _ => options,
+        };
+        let result = T::deserialize(Deserializer::with_options(vars, Some(options)));
+        if let Some(max) = self.max_vars.filter(|max| count.get() > *max) {
+            return Err(Error::Custom(format!(
+                "too many env vars provided, the limit is {}",
+                max
+            )));
+        }
+        if let Ok(resolved) = resolved.lock() {
+            seen.borrow_mut().extend(
+                resolved
+                    .iter()
+                    .map(|(field, value)| (self.options.normalize(field), value.clone())),
+            );
+        }
+        let result = result.and_then(|value| {
+            let seen = seen.borrow();
+            let unless = |condition: &str| {
+                seen.get(&self.options.normalize(condition))
+                    .and_then(|value| self.options.parse_bool(value).ok())
+                    .unwrap_or(false)
+            };
+            match self
+                .required
+                .iter()
+                .chain(
+                    self.required_unless
+                        .iter()
+                        .filter(|(_, condition)| !unless(condition))
+                        .map(|(field, _)| field),
+                )
+                .find(|field| !seen.contains_key(&self.options.normalize(field)))
+            {
+                Some(field) => Err(Error::MissingValue(field.clone())),
+                None => Ok(value),
+            }
+        });
+        result.map_err(|error| match error {
+            Error::MissingValue(var) => Error::MissingValue(self.var_name(&var)),
+            Error::UnknownField(var) => Error::UnknownField(self.var_name(&var)),
+            error => error,
+        })
+    }
+}
+
+/// A [Config](struct.Config.html) which applies dependent defaults to deserialized values
+///
+/// These types are created with
+/// [Config::with_dependent_defaults](struct.Config.html#method.with_dependent_defaults).
+pub struct DependentDefaults<T, F> {
+    config: Config,
+    defaults: F,
+    marker: PhantomData<fn(&mut T)>,
+}
+
+impl<T, F> DependentDefaults<T, F>
+where
+    T: serde::de::DeserializeOwned,
+    F: Fn(&mut T),
+{
+    /// Deserializes a type based on env variables
+    pub fn from_env(&self) -> Result<T> {
+        self.from_iter(env::vars())
+    }
+
+    /// Deserializes a type based on (String, String) tuples
+    pub fn from_iter<Iter>(
+        &self,
+        iter: Iter,
+    ) -> Result<T>
+    where
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        let mut value = self.config.from_iter(iter)?;
+        (self.defaults)(&mut value);
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use std::{collections::HashMap, time::Duration};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    #[derive(Default)]
+    pub enum Size {
+        Small,
+        #[default]
+        Medium,
+        Large,
+    }
+
+    pub fn default_kaboom() -> u16 {
+        8080
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct CustomNewType(u32);
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Foo {
+        bar: String,
+        baz: bool,
+        zoom: Option<u16>,
+        doom: Vec<u64>,
+        boom: Vec<String>,
+        #[serde(default = "default_kaboom")]
+        kaboom: u16,
+        #[serde(default)]
+        debug_mode: bool,
+        #[serde(default)]
+        size: Size,
+        provided: Option<String>,
+        newtype: CustomNewType,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct CrazyFoo {
+        #[serde(rename = "BaR")]
+        bar: String,
+        #[serde(rename = "SCREAMING_BAZ")]
+        screaming_baz: bool,
+        zoom: Option<u16>,
+    }
+
+    #[test]
+    fn deserialize_from_iter() {
+        let data = vec![
+            (String::from("BAR"), String::from("test")),
+            (String::from("BAZ"), String::from("true")),
+            (String::from("DOOM"), String::from("1, 2, 3 ")),
+            // Empty string should result in empty vector.
+            (String::from("BOOM"), String::from("")),
+            (String::from("SIZE"), String::from("small")),
+            (String::from("PROVIDED"), String::from("test")),
+            (String::from("NEWTYPE"), String::from("42")),
+        ];
+        match from_iter::<_, Foo>(data) {
+            Ok(actual) => assert_eq!(
+                actual,
+                Foo {
+                    bar: String::from("test"),
+                    baz: true,
+                    zoom: None,
+                    doom: vec![1, 2, 3],
+                    boom: vec![],
+                    kaboom: 8080,
+                    debug_mode: false,
+                    size: Size::Small,
+                    provided: Some(String::from("test")),
+                    newtype: CustomNewType(42)
+                }
+            ),
+            Err(e) => panic!("{:#?}", e),
+        }
+    }
+
+    #[test]
+    fn fails_with_missing_value() {
+        let data = vec![
+            (String::from("BAR"), String::from("test")),
+            (String::from("BAZ"), String::from("true")),
+        ];
+        match from_iter::<_, Foo>(data) {
+            Ok(_) => panic!("expected failure"),
+            Err(e) => assert_eq!(e, Error::MissingValue("DOOM".into())),
+        }
+    }
+
+    #[test]
+    fn prefixed_fails_with_missing_value() {
+        let data = vec![
+            (String::from("PREFIX_BAR"), String::from("test")),
+            (String::from("PREFIX_BAZ"), String::from("true")),
+        ];
+
+        match prefixed("PREFIX_").from_iter::<_, Foo>(data) {
+            Ok(_) => panic!("expected failure"),
+            Err(e) => assert_eq!(e, Error::MissingValue("PREFIX_DOOM".into())),
+        }
+    }
+
+    #[test]
+    fn fails_with_missing_value_template() {
+        let data = vec![
+            (String::from("BAR"), String::from("test")),
+            (String::from("BAZ"), String::from("true")),
+        ];
+        let config = Config::new()
+            .missing_template("Configuration error: {var} is not set (see docs/config.md)");
+        match config.from_iter::<_, Foo>(data) {
+            Ok(_) => panic!("expected failure"),
+            Err(e) => {
+                assert_eq!(e, Error::MissingValue(String::from("DOOM")));
+                assert_eq!(
+                    config.display_error(&e).to_string(),
+                    "Configuration error: DOOM is not set (see docs/config.md)"
+                );
+                assert_eq!(e.to_string(), "missing value for DOOM");
+            }
+        }
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Worker {
+        name: String,
+        threads: u8,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Workers {
+        worker: Vec<Worker>,
+    }
+
+    #[test]
+    fn deserializes_indexed_nested_structs() {
+        let data = vec![
+            (String::from("WORKER_1__NAME"), String::from("beta")),
+            (String::from("WORKER_0__NAME"), String::from("alpha")),
+            (String::from("WORKER_0__THREADS"), String::from("2")),
+            (String::from("WORKER_1__THREADS"), String::from("4")),
+        ];
+        match Config::new().nested(true).from_iter::<_, Workers>(data) {
+            Ok(actual) => assert_eq!(
+                actual,
+                Workers {
+                    worker: vec![
+                        Worker {
+                            name: String::from("alpha"),
+                            threads: 2
+                        },
+                        Worker {
+                            name: String::from("beta"),
+                            threads: 4
+                        }
+                    ]
+                }
+            ),
+            Err(e) => panic!("{:#?}", e),
+        }
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Smtp {
+        host: String,
+        port: Option<u16>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Mailer {
+        smtp_mode: String,
+        smtp_host_fallback: Option<String>,
+        smtp: Option<Smtp>,
+    }
+
+    #[test]
+    fn optional_nested_struct_is_none_without_its_namespace() {
+        let data = vec![
+            (String::from("SMTP_MODE"), String::from("relay")),
+            (
+                String::from("SMTP_HOST_FALLBACK"),
+                String::from("localhost"),
+            ),
+        ];
+        match Config::new().nested(true).from_iter::<_, Mailer>(data) {
+            Ok(actual) => assert_eq!(actual.smtp, None),
+            Err(e) => panic!("{:#?}", e),
+        }
+    }
+
+    #[test]
+    fn optional_nested_struct_is_some_with_its_namespace() {
+        let data = vec![
+            (String::from("SMTP_MODE"), String::from("relay")),
+            (String::from("SMTP__HOST"), String::from("mail.example.com")),
+        ];
+        match Config::new().nested(true).from_iter::<_, Mailer>(data) {
+            Ok(actual) => assert_eq!(
+                actual.smtp,
+                Some(Smtp {
+                    host: String::from("mail.example.com"),
+                    port: None
+                })
+            ),
+            Err(e) => panic!("{:#?}", e),
+        }
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Shared {
+        smtp: std::sync::Arc<Smtp>,
+        backup: std::rc::Rc<Smtp>,
+    }
+
+    #[test]
+    fn deserializes_shared_nested_structs() {
+        let data = vec![
+            (String::from("SMTP__HOST"), String::from("mail.example.com")),
+            (String::from("SMTP__PORT"), String::from("25")),
+            (
+                String::from("BACKUP__HOST"),
+                String::from("backup.example.com"),
+            ),
+        ];
+        match Config::new().nested(true).from_iter::<_, Shared>(data) {
+            Ok(actual) => {
+                assert_eq!(
+                    *actual.smtp,
+                    Smtp {
+                        host: String::from("mail.example.com"),
+                        port: Some(25)
+                    }
+                );
+                assert_eq!(
+                    *actual.backup,
+                    Smtp {
+                        host: String::from("backup.example.com"),
+                        port: None
+                    }
+                );
+            }
+            Err(e) => panic!("{:#?}", e),
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+    pub struct Database {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct App {
+        db: Database,
+    }
+
+    #[test]
+    fn nests_after_stripping_prefix_containing_separator() {
+        let data = vec![
+            (String::from("APP__DB__HOST"), String::from("localhost")),
+            (String::from("APP__DB__PORT"), String::from("5432")),
+            (String::from("OTHER__DB__HOST"), String::from("elsewhere")),
+        ];
+        assert_eq!(
+            Config::new()
+                .prefix("APP__")
+                .nested(true)
+                .from_iter::<_, App>(data),
+            Ok(App {
+                db: Database {
+                    host: String::from("localhost"),
+                    port: 5432
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn prefixed_nested_fails_with_full_var_name() {
+        let data = vec![(String::from("APP__DB__HOST"), String::from("localhost"))];
+        assert_eq!(
+            Config::new()
+                .prefix("APP__")
+                .nested(true)
+                .from_iter::<_, App>(data),
+            Err(Error::MissingValue(String::from("APP__DB__PORT")))
+        );
+    }
+
+    #[test]
+    fn indexed_nested_structs_fail_with_gaps() {
+        let data = vec![
+            (String::from("WORKER_0__NAME"), String::from("alpha")),
+            (String::from("WORKER_0__THREADS"), String::from("2")),
+            (String::from("WORKER_2__NAME"), String::from("gamma")),
+            (String::from("WORKER_2__THREADS"), String::from("4")),
+        ];
+        match Config::new().nested(true).from_iter::<_, Workers>(data) {
+            Ok(_) => panic!("expected failure"),
+            Err(e) => assert_eq!(
+                e,
+                Error::Custom(String::from(
+                    "indexed vars for WORKER must be contiguous from 0 but index 1 is missing"
+                ))
+            ),
+        }
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Regions {
+        region: String,
+        region_1: Worker,
+    }
+
+    #[test]
+    fn groups_indexed_namespaces_only_for_sequences() {
+        let data = vec![
+            (String::from("REGION"), String::from("eu")),
+            (String::from("REGION_1__NAME"), String::from("alpha")),
+            (String::from("REGION_1__THREADS"), String::from("2")),
+        ];
+        assert_eq!(
+            Config::new().nested(true).from_iter::<_, Regions>(data),
+            Ok(Regions {
+                region: String::from("eu"),
+                region_1: Worker {
+                    name: String::from("alpha"),
+                    threads: 2
+                }
+            })
+        );
+        let data = vec![
+            (String::from("WORKER"), String::from("alpha")),
+            (String::from("WORKER_0__NAME"), String::from("alpha")),
+            (String::from("WORKER_0__THREADS"), String::from("2")),
+        ];
+        assert_eq!(
+            Config::new().nested(true).from_iter::<_, Workers>(data),
+            Err(Error::Custom(String::from(
+                "WORKER can not be set along with indexed vars like WORKER_0"
+            )))
+        );
+    }
+
+    #[test]
+    fn indexed_nested_structs_fail_with_missing_value() {
+        let data = vec![
+            (String::from("WORKER_0__NAME"), String::from("alpha")),
+            (String::from("WORKER_0__THREADS"), String::from("2")),
+            (String::from("WORKER_1__NAME"), String::from("beta")),
+        ];
+        match Config::new().nested(true).from_iter::<_, Workers>(data) {
+            Ok(_) => panic!("expected failure"),
+            Err(e) => assert_eq!(e, Error::MissingValue("WORKER_1__THREADS".into())),
+        }
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Orchestrated {
+        app_db_host: String,
+        app_db_port: u16,
+    }
+
+    #[test]
+    fn deserializes_normalized_separators() {
+        let data = vec![
+            (String::from("app.db.host"), String::from("localhost")),
+            (String::from("APP-DB-PORT"), String::from("5432")),
+        ];
+        match Config::new()
+            .normalize_separators(true)
+            .from_iter::<_, Orchestrated>(data)
+        {
+            Ok(actual) => assert_eq!(
+                actual,
+                Orchestrated {
+                    app_db_host: String::from("localhost"),
+                    app_db_port: 5432
+                }
+            ),
+            Err(e) => panic!("{:#?}", e),
+        }
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Wide {
+        big: u128,
+        small: i128,
+    }
+
+    #[test]
+    fn deserializes_128_bit_integers() {
+        let data = vec![
+            (
+                String::from("BIG"),
+                String::from("340282366920938463463374607431768211455"),
+            ),
+            (
+                String::from("SMALL"),
+                String::from("-170141183460469231731687303715884105728"),
+            ),
+        ];
+        match from_iter::<_, Wide>(data) {
+            Ok(actual) => assert_eq!(
+                actual,
+                Wide {
+                    big: u128::MAX,
+                    small: i128::MIN
+                }
+            ),
+            Err(e) => panic!("{:#?}", e),
+        }
+    }
+
+    #[test]
+    fn resolves_missing_fields() {
+        let data = vec![
+            (String::from("BAR"), String::from("test")),
+            (String::from("BAZ"), String::from("true")),
+            (String::from("BOOM"), String::from("a,b")),
+            (String::from("NEWTYPE"), String::from("42")),
+        ];
+        let config = Config::new().resolve(|field| match field {
+            "doom" => Some(String::from("1,2")),
+            "bar" => Some(String::from("resolved")),
+            _ => None,
+        });
+        match config.from_iter::<_, Foo>(data) {
+            Ok(actual) => {
+                assert_eq!(actual.doom, vec![1, 2]);
+                assert_eq!(actual.bar, "test");
+                assert_eq!(actual.provided, None);
+            }
+            Err(e) => panic!("{:#?}", e),
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq, Default)]
+    pub struct Flag {
+        flag: bool,
+    }
+
+    #[test]
+    fn deserializes_pg_bools() {
+        for (value, expected) in [("t", true), ("f", false), ("T", true), ("F", false)] {
+            let data = vec![(String::from("FLAG"), String::from(value))];
+            assert_eq!(
+                Config::new().pg_bools(true).from_iter::<_, Flag>(data),
+                Ok(Flag { flag: expected })
+            );
+        }
+    }
+
+    #[test]
+    fn pg_bools_fail_with_invalid_token() {
+        let data = vec![(String::from("FLAG"), String::from("maybe"))];
+        assert_eq!(
+            Config::new().pg_bools(true).from_iter::<_, Flag>(data),
+            Err(Error::InvalidValue {
+                var: String::from("FLAG"),
+                value: String::from("maybe"),
+                code: ErrorCode::InvalidBool,
+                message: String::from("provided string was not a recognized boolean")
+            })
+        );
+    }
+
+    #[test]
+    fn deserializes_bool_tokens() {
+        let config = Config::new().bool_tokens(["yes", "on"], ["no", "off"]);
+        for (value, expected) in [("YES", true), ("on", true), ("No", false), ("true", true)] {
+            let data = vec![(String::from("FLAG"), String::from(value))];
+            assert_eq!(
+                config.from_iter::<_, Flag>(data),
+                Ok(Flag { flag: expected })
+            );
+        }
+        let data = vec![(String::from("FLAG"), String::from("1"))];
+        assert_eq!(
+            config.from_iter::<_, Flag>(data),
+            Err(Error::InvalidValue {
+                var: String::from("FLAG"),
+                value: String::from("1"),
+                code: ErrorCode::InvalidBool,
+                message: String::from("provided string was not `true` or `false`")
+            })
+        );
+    }
+
+    #[test]
+    fn bools_are_strict_by_default() {
+        let data = vec![(String::from("FLAG"), String::from("t"))];
+        assert!(from_iter::<_, Flag>(data).is_err());
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    pub struct Credentials {
+        user: String,
+        api_key: String,
+        retries: Option<u8>,
+    }
+
+    #[test]
+    fn records_audit_entries() {
+        let data = vec![
+            (String::from("USER"), String::from("admin")),
+            (String::from("API_KEY"), String::from("hunter2")),
+            (String::from("RETRIES"), String::from("3")),
+            (String::from("UNRELATED"), String::from("ignored")),
+        ];
+        match Config::new()
+            .sensitive(["api_key"])
+            .from_iter_audited::<_, Credentials>(data)
+        {
+            Ok((_, entries)) => assert_eq!(
+                entries,
+                vec![
+                    AuditEntry {
+                        var: String::from("USER"),
+                        value: String::from("admin"),
+                        parsed_ok: true
+                    },
+                    AuditEntry {
+                        var: String::from("API_KEY"),
+                        value: String::from("***"),
+                        parsed_ok: true
+                    },
+                    AuditEntry {
+                        var: String::from("RETRIES"),
+                        value: String::from("3"),
+                        parsed_ok: true
+                    },
+                ]
+            ),
+            Err(e) => panic!("{:#?}", e),
+        }
+    }
+
+    #[test]
+    fn redacts_sensitive_vars_by_prefixed_name() {
+        let data = vec![
+            (String::from("APP_USER"), String::from("admin")),
+            (String::from("APP_API_KEY"), String::from("hunter2")),
+        ];
+        let config = Config::new().prefix("APP_").sensitive(["APP_API_KEY"]);
+        match config.from_iter_audited::<_, Credentials>(data.clone()) {
+            Ok((_, entries)) => assert_eq!(
+                entries,
+                vec![
+                    AuditEntry {
+                        var: String::from("APP_USER"),
+                        value: String::from("admin"),
+                        parsed_ok: true
+                    },
+                    AuditEntry {
+                        var: String::from("APP_API_KEY"),
+                        value: String::from("***"),
+                        parsed_ok: true
+                    },
+                ]
+            ),
+            Err(e) => panic!("{:#?}", e),
+        }
+        let credentials = Credentials {
+            user: String::from("admin"),
+            api_key: String::from("hunter2"),
+            retries: None,
+        };
+        assert_eq!(
+            config.to_vec_redacted(&credentials),
+            Ok(vec![
+                (String::from("USER"), String::from("admin")),
+                (String::from("API_KEY"), String::from("***")),
+            ])
+        );
+        let fingerprint = |api_key: &str| {
+            config
+                .from_iter_fingerprinted::<_, Credentials>(vec![
+                    (String::from("APP_USER"), String::from("admin")),
+                    (String::from("APP_API_KEY"), String::from(api_key)),
+                ])
+                .map(|(_, fingerprint)| fingerprint)
+        };
+        assert_eq!(fingerprint("hunter2"), fingerprint("hunter3"));
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Routing {
+        upstreams: Vec<(String, u32)>,
+    }
+
+    #[test]
+    fn deserializes_weighted_lists() {
+        let data = vec![(String::from("UPSTREAMS"), String::from("a:3, b:1, c:2"))];
+        assert_eq!(
+            from_iter::<_, Routing>(data),
+            Ok(Routing {
+                upstreams: vec![
+                    (String::from("a"), 3),
+                    (String::from("b"), 1),
+                    (String::from("c"), 2)
+                ]
+            })
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Weighted {
+        upstream: (String, u32),
+    }
+
+    #[test]
+    fn deserializes_tuples() {
+        let data = vec![(String::from("UPSTREAM"), String::from("a:3"))];
+        assert_eq!(
+            from_iter::<_, Weighted>(data),
+            Ok(Weighted {
+                upstream: (String::from("a"), 3)
+            })
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Endpoints {
+        endpoints: Vec<(std::net::IpAddr, u16)>,
+    }
+
+    #[test]
+    fn deserializes_endpoint_lists() {
+        let endpoints = |value: &str| {
+            let data = vec![(String::from("ENDPOINTS"), String::from(value))];
+            from_iter::<_, Endpoints>(data).map(|e| e.endpoints)
+        };
+        assert_eq!(
+            endpoints("1.2.3.4:80,5.6.7.8:443"),
+            Ok(vec![
+                ("1.2.3.4".parse().unwrap(), 80),
+                ("5.6.7.8".parse().unwrap(), 443)
+            ])
+        );
+        assert_eq!(
+            endpoints("[::1]:80"),
+            Ok(vec![("::1".parse().unwrap(), 80)])
+        );
+        assert_eq!(
+            endpoints("1.2.3.4:80,5.6.7.8:http"),
+            Err(Error::SeqElement {
+                var: String::from("ENDPOINTS"),
+                index: 1,
+                value: String::from("http"),
+                code: ErrorCode::InvalidInteger,
+                message: String::from("invalid digit found in string")
+            })
+        );
+        assert_eq!(
+            endpoints("1.2.3.4:80,5.6.7.8"),
+            Err(Error::SeqElement {
+                var: String::from("ENDPOINTS"),
+                index: 1,
+                value: String::from("5.6.7.8"),
+                code: ErrorCode::Invalid,
+                message: String::from("invalid length 1, expected a tuple of size 2")
+            })
+        );
+    }
+
+    #[test]
+    fn weighted_lists_fail_with_invalid_weight() {
+        let data = vec![(String::from("UPSTREAMS"), String::from("a:3,b:heavy"))];
+        assert_eq!(
+            from_iter::<_, Routing>(data),
+            Err(Error::SeqElement {
+                var: String::from("UPSTREAMS"),
+                index: 1,
+                value: String::from("heavy"),
+                code: ErrorCode::InvalidInteger,
+                message: String::from("invalid digit found in string")
+            })
+        );
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    #[serde(default)]
+    pub struct Lenient {
+        port: u16,
+        host: String,
+        debug: bool,
+    }
+
+    impl Default for Lenient {
+        fn default() -> Self {
+            Lenient {
+                port: 8080,
+                host: String::from("localhost"),
+                debug: false,
+            }
+        }
+    }
+
+    #[test]
+    fn infallible_falls_back_to_defaults() {
+        let data = vec![
+            (String::from("PORT"), String::from("not-a-port")),
+            (String::from("DEBUG"), String::from("sometimes")),
+        ];
+        let mut warnings = Vec::new();
+        let actual: Lenient =
+            Config::new().from_iter_infallible(data, |error| warnings.push(error.clone()));
+        assert_eq!(actual, Lenient::default());
+        assert_eq!(warnings.len(), 2);
+    }
+
+    #[test]
+    fn infallible_keeps_valid_values() {
+        let data = vec![
+            (String::from("PORT"), String::from("not-a-port")),
+            (String::from("HOST"), String::from("example.com")),
+        ];
+        let actual: Lenient = Config::new().from_iter_infallible(data, |_| {});
+        assert_eq!(
+            actual,
+            Lenient {
+                host: String::from("example.com"),
+                ..Lenient::default()
+            }
+        );
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    pub struct Backend {
+        name: String,
+        host: String,
+        port: u16,
+    }
+
+    impl Default for Backend {
+        fn default() -> Self {
+            Backend {
+                name: String::from("service"),
+                host: String::from("localhost"),
+                port: 8080,
+            }
+        }
+    }
+
+    #[test]
+    fn infallible_falls_back_to_default_for_missing_values() {
+        let mut warnings = Vec::new();
+        let actual: Flag =
+            Config::new().from_iter_infallible(vec![], |error| warnings.push(error.clone()));
+        assert_eq!(actual, Flag::default());
+        assert_eq!(warnings, vec![Error::MissingValue(String::from("FLAG"))]);
+    }
+
+    #[test]
+    fn infallible_fills_each_missing_field_from_default() {
+        let data = vec![
+            (String::from("APP_NAME"), String::from("api")),
+            (String::from("APP_PORT"), String::from("not-a-port")),
+        ];
+        let mut warnings = Vec::new();
+        let actual: Backend = Config::new()
+            .prefix("APP_")
+            .from_iter_infallible(data, |error| warnings.push(error.code()));
+        assert_eq!(
+            actual,
+            Backend {
+                name: String::from("api"),
+                ..Backend::default()
+            }
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                ErrorCode::InvalidInteger,
+                ErrorCode::MissingRequired,
+                ErrorCode::MissingRequired
+            ]
+        );
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    pub struct Proxy {
+        labels: BTreeMap<String, String>,
+        token: Secret<String>,
+        backend: Backend,
+    }
+
+    impl Default for Proxy {
+        fn default() -> Self {
+            Proxy {
+                labels: BTreeMap::from([(String::from("team"), String::from("a,b"))]),
+                token: Secret::new(String::from("dev-token")),
+                backend: Backend::default(),
+            }
+        }
+    }
+
+    #[test]
+    fn infallible_fills_maps_secrets_and_nested_fields_from_default() {
+        let data = vec![
+            (String::from("APP_BACKEND__NAME"), String::from("api")),
+            (
+                String::from("APP_BACKEND__PORT"),
+                String::from("not-a-port"),
+            ),
+        ];
+        let mut warnings = Vec::new();
+        let actual: Proxy = Config::new()
+            .prefix("APP_")
+            .nested(true)
+            .from_iter_infallible(data, |error| warnings.push(error.code()));
+        assert_eq!(actual.labels, Proxy::default().labels);
+        assert_eq!(actual.token.expose(), "dev-token");
+        assert_eq!(
+            actual.backend,
+            Backend {
+                name: String::from("api"),
+                ..Backend::default()
+            }
+        );
+        assert_eq!(warnings[0], ErrorCode::InvalidInteger);
+        assert_eq!(warnings[1..], [ErrorCode::MissingRequired; 4]);
+    }
+
+    #[test]
+    fn case_insensitive_matches_renamed_fields() {
+        let data = vec![
+            (String::from("BAR"), String::from("test")),
+            (String::from("screaming_baz"), String::from("true")),
+            (String::from("ZOOM"), String::from("8080")),
+        ];
+        match Config::new()
+            .case_insensitive(true)
+            .from_iter::<_, CrazyFoo>(data)
+        {
+            Ok(actual) => assert_eq!(
+                actual,
+                CrazyFoo {
+                    bar: String::from("test"),
+                    screaming_baz: true,
+                    zoom: Some(8080),
+                }
+            ),
+            Err(e) => panic!("{:#?}", e),
+        }
+    }
+
+    #[test]
+    fn case_insensitive_is_disabled_by_default() {
+        let data = vec![
+            (String::from("BAR"), String::from("test")),
+            (String::from("SCREAMING_BAZ"), String::from("true")),
+        ];
+        assert!(from_iter::<_, CrazyFoo>(data.clone()).is_err());
+        assert!(Config::new()
+            .case_insensitive(false)
+            .from_iter::<_, CrazyFoo>(data)
+            .is_err());
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    pub struct Dumped {
+        host: String,
+        ports: Vec<u16>,
+        api_key: String,
+        timeout: Option<u32>,
+    }
+
+    #[test]
+    fn dumps_effective_config() {
+        let data = vec![
+            (String::from("HOST"), String::from("localhost")),
+            (String::from("PORTS"), String::from("80, 443")),
+            (String::from("API_KEY"), String::from("hunter2")),
+        ];
+        let path = env::temp_dir().join(format!("envy-dump-{}.env", std::process::id()));
+        let actual = Config::new()
+            .sensitive(["API_KEY"])
+            .from_iter_and_dump::<_, Dumped, _>(data, &path);
+        let contents = fs::read_to_string(&path).expect("failed to read dump");
+        fs::remove_file(&path).expect("failed to remove dump");
+        assert_eq!(
+            actual,
+            Ok(Dumped {
+                host: String::from("localhost"),
+                ports: vec![80, 443],
+                api_key: String::from("hunter2"),
+                timeout: None,
+            })
+        );
+        assert_eq!(contents, "HOST=localhost\nPORTS=80,443\nAPI_KEY=***\n");
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Localized {
+        debug: bool,
+        size: Size,
+        sizes: Vec<Size>,
+    }
+
+    #[test]
+    fn deserializes_value_aliases() {
+        let data = vec![
+            (String::from("DEBUG"), String::from("oui")),
+            (String::from("SIZE"), String::from("grand")),
+            (String::from("SIZES"), String::from("grand,small")),
+        ];
+        let config =
+            Config::new().value_aliases([("oui", "true"), ("non", "false"), ("grand", "large")]);
+        assert_eq!(
+            config.from_iter::<_, Localized>(data),
+            Ok(Localized {
+                debug: true,
+                size: Size::Large,
+                sizes: vec![Size::Large, Size::Small]
+            })
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Borrowing {
+        name: Cow<'static, str>,
+    }
+
+    #[test]
+    fn deserializes_cow_str_as_owned() {
+        let data = vec![(String::from("NAME"), String::from("envy"))];
+        match from_iter::<_, Borrowing>(data) {
+            Ok(actual) => assert!(matches!(actual.name, Cow::Owned(name) if name == "envy")),
+            Err(e) => panic!("{:#?}", e),
+        }
+    }
+
+    crate::config! {
+        #[derive(Debug, PartialEq)]
+        pub struct Documented {
+            host: String => "The host to bind to",
+            #[serde(default)]
+            port: Option<u16> => "The port to listen on",
+        }
+    }
+
+    #[test]
+    fn config_macro_lists_help() {
+        assert_eq!(
+            Documented::help(),
+            "HOST\tThe host to bind to\nPORT\tThe port to listen on\n"
+        );
+        let data = vec![(String::from("HOST"), String::from("localhost"))];
+        assert_eq!(
+            from_iter::<_, Documented>(data),
+            Ok(Documented {
+                host: String::from("localhost"),
+                port: None
+            })
+        );
+    }
+
+    #[test]
+    fn deserializes_from_file() {
+        let path = env::temp_dir().join(format!("envy-file-{}.env", std::process::id()));
+        fs::write(
+            &path,
+            "# the database\n\nexport HOST=\"db host\"\nPORT='5432' # default\n",
+        )
+        .expect("failed to write .env");
+        let actual = from_file::<_, Database>(&path);
+        fs::remove_file(&path).expect("failed to remove .env");
+        assert_eq!(
+            actual,
+            Ok(Database {
+                host: String::from("db host"),
+                port: 5432
+            })
+        );
+        assert!(from_file::<_, Database>(&path).is_err());
+    }
+
+    #[test]
+    fn later_dotenv_files_override_earlier() {
+        let dir = env::temp_dir().join(format!("envy-dotenv-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("failed to create dir");
+        let base = dir.join(".env");
+        let local = dir.join(".env.local");
+        fs::write(&base, "HOST=localhost\nPORT=5432\n").expect("failed to write .env");
+        fs::write(&local, "# overrides\nexport PORT=6543\n").expect("failed to write .env.local");
+        let missing = dir.join(".env.production");
+        let actual = Config::new()
+            .skip_missing_files(true)
+            .from_dotenv_files::<Database, _>(&[&base, &local, &missing]);
+        let strict = from_dotenv_files::<Database, _>(&[&base, &missing]);
+        fs::remove_dir_all(&dir).expect("failed to remove dir");
+        assert_eq!(
+            actual,
+            Ok(Database {
+                host: String::from("localhost"),
+                port: 6543
+            })
+        );
+        assert!(strict.is_err());
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Toggle {
+        Yes,
+        No,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Toggles {
+        toggle: Toggle,
+    }
+
+    #[test]
+    fn deserializes_case_insensitive_enums() {
+        for (value, expected) in [
+            ("Yes", Toggle::Yes),
+            ("no", Toggle::No),
+            ("YES", Toggle::Yes),
+        ] {
+            let data = vec![(String::from("TOGGLE"), String::from(value))];
+            assert_eq!(
+                Config::new()
+                    .case_insensitive_enums(true)
+                    .from_iter::<_, Toggles>(data),
+                Ok(Toggles { toggle: expected })
+            );
+        }
+    }
+
+    #[test]
+    fn enums_are_case_sensitive_by_default() {
+        let data = vec![(String::from("TOGGLE"), String::from("YES"))];
+        assert!(from_iter::<_, Toggles>(data).is_err());
+    }
+
+    #[test]
+    fn overrides_take_precedence_over_env() {
+        let data = vec![
+            (String::from("HOST"), String::from("localhost")),
+            (String::from("PORT"), String::from("5432")),
+        ];
+        let overrides = vec![(String::from("port"), String::from("6543"))];
+        assert_eq!(
+            Config::new().from_iter_with_overrides::<_, _, Database>(data, overrides),
+            Ok(Database {
+                host: String::from("localhost"),
+                port: 6543
+            })
+        );
+    }
+
+    #[test]
+    fn overrides_take_precedence_over_prefixed_env() {
+        let data = vec![
+            (String::from("APP_HOST"), String::from("localhost")),
+            (String::from("APP_PORT"), String::from("5432")),
+        ];
+        for name in ["port", "APP_PORT"] {
+            let overrides = vec![(String::from(name), String::from("90"))];
+            assert_eq!(
+                Config::new()
+                    .prefix("APP_")
+                    .from_iter_with_overrides::<_, _, Database>(data.clone(), overrides),
+                Ok(Database {
+                    host: String::from("localhost"),
+                    port: 90
+                })
+            );
+        }
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Level {
+        Info,
+        Warn,
+        #[serde(other)]
+        Unknown,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Logging {
+        level: Level,
+    }
+
+    #[test]
+    fn deserializes_enum_fallback_variant() {
+        for (value, expected) in [("warn", Level::Warn), ("trace", Level::Unknown)] {
+            let data = vec![(String::from("LEVEL"), String::from(value))];
+            assert_eq!(
+                from_iter::<_, Logging>(data),
+                Ok(Logging { level: expected })
+            );
+        }
+    }
+
+    #[test]
+    fn fails_with_too_many_vars() {
+        let data = (0..10).map(|i| (format!("VAR_{}", i), i.to_string()));
+        assert_eq!(
+            Config::new()
+                .max_vars(5)
+                .from_iter::<_, HashMap<String, String>>(data),
+            Err(Error::Custom(String::from(
+                "too many env vars provided, the limit is 5"
+            )))
+        );
+    }
+
+    #[test]
+    fn deserializes_within_max_vars() {
+        let data = vec![
+            (String::from("HOST"), String::from("localhost")),
+            (String::from("PORT"), String::from("5432")),
+        ];
+        assert_eq!(
+            Config::new().max_vars(2).from_iter::<_, Database>(data),
+            Ok(Database {
+                host: String::from("localhost"),
+                port: 5432
+            })
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Nullable {
+        timeout: Option<u32>,
+        label: Option<String>,
+        name: String,
+    }
+
+    #[test]
+    fn deserializes_global_null_as_none() {
+        let data = vec![
+            (String::from("TIMEOUT"), String::from("__NULL__")),
+            (String::from("LABEL"), String::from("__NULL__")),
+            (String::from("NAME"), String::from("__NULL__")),
+        ];
+        assert_eq!(
+            Config::new()
+                .global_null("__NULL__")
+                .from_iter::<_, Nullable>(data),
+            Ok(Nullable {
+                timeout: None,
+                label: None,
+                name: String::from("__NULL__")
+            })
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Timeouts {
+        #[serde(with = "crate::duration")]
+        timeout: Duration,
+        #[serde(with = "crate::duration")]
+        retry: Duration,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct PlainTimeout {
+        timeout: Duration,
+    }
+
+    #[test]
+    fn deserializes_durations_with_default_unit() {
+        let data = vec![
+            (String::from("TIMEOUT"), String::from("1500")),
+            (String::from("RETRY"), String::from("2s")),
+        ];
+        assert_eq!(
+            from_iter::<_, Timeouts>(data.clone()),
+            Ok(Timeouts {
+                timeout: Duration::from_secs(1500),
+                retry: Duration::from_secs(2)
+            })
+        );
+        assert_eq!(
+            Config::new()
+                .duration_default_unit(Unit::Millis)
+                .from_iter::<_, Timeouts>(data.clone()),
+            Ok(Timeouts {
+                timeout: Duration::from_millis(1500),
+                retry: Duration::from_secs(2)
+            })
+        );
+        // fields without the with module are not read from human friendly values
+        assert!(Config::new()
+            .duration_default_unit(Unit::Millis)
+            .from_iter::<_, PlainTimeout>(data)
+            .is_err());
+    }
+
+    #[test]
+    fn fails_with_invalid_duration() {
+        let data = vec![(String::from("TIMEOUT"), String::from("soon"))];
+        match from_iter::<_, Timeouts>(data) {
+            Err(e) => assert_eq!(
+                e,
+                Error::InvalidValue {
+                    var: String::from("TIMEOUT"),
+                    value: String::from("soon"),
+                    code: ErrorCode::Invalid,
+                    message: String::from("invalid duration")
+                }
+            ),
+            Ok(_) => panic!("expected failure"),
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    pub struct Fingerprinted {
+        host: String,
+        password: String,
+        #[serde(default)]
+        labels: HashMap<String, String>,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    pub struct Canonical {
+        port: u16,
+        debug: bool,
+        tags: Vec<String>,
+        name: String,
+    }
+
+    #[test]
+    fn canonicalizes_config() {
+        let data = vec![
+            (String::from("APP_TAGS"), String::from("web, api")),
+            (String::from("APP_PORT"), String::from("080")),
+            (String::from("APP_NAME"), String::from("demo")),
+            (String::from("APP_DEBUG"), String::from("yes")),
+        ];
+        let pair = |key: &str, value: &str| (String::from(key), String::from(value));
+        assert_eq!(
+            Config::new()
+                .prefix("APP_")
+                .pg_bools(true)
+                .canonicalize_iter::<_, Canonical>(data),
+            Ok(vec![
+                pair("APP_DEBUG", "true"),
+                pair("APP_NAME", "demo"),
+                pair("APP_PORT", "80"),
+                pair("APP_TAGS", "web,api"),
+            ])
+        );
+    }
+
+    #[test]
+    fn fingerprints_effective_config() {
+        let config = Config::new().nested(true).sensitive(["password"]);
+        let fingerprint = |vars: &[(&str, &str)]| {
+            let vars = vars.iter().map(|(k, v)| (k.to_string(), v.to_string()));
+            config
+                .from_iter_fingerprinted::<_, Fingerprinted>(vars)
+                .map(|(_, fingerprint)| fingerprint)
+                .unwrap()
+        };
+        let original = fingerprint(&[
+            ("HOST", "localhost"),
+            ("PASSWORD", "secret"),
+            ("LABELS__A", "1"),
+            ("LABELS__B", "2"),
+        ]);
+        assert_eq!(
+            original,
+            fingerprint(&[
+                ("LABELS__B", "2"),
+                ("PASSWORD", "changed"),
+                ("LABELS__A", "1"),
+                ("HOST", "localhost"),
+            ])
+        );
+        assert_ne!(
+            original,
+            fingerprint(&[
+                ("HOST", "example.com"),
+                ("PASSWORD", "secret"),
+                ("LABELS__A", "1"),
+                ("LABELS__B", "2"),
+            ])
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Presence {
+        verbose: Option<()>,
+        quiet: Option<()>,
+    }
+
+    #[test]
+    fn deserializes_unit_options_from_presence() {
+        let data = vec![(String::from("VERBOSE"), String::new())];
+        assert_eq!(
+            from_iter::<_, Presence>(data),
+            Ok(Presence {
+                verbose: Some(()),
+                quiet: None
+            })
+        );
+    }
+
+    #[test]
+    fn deserializes_transformed_values() {
+        let data = vec![
+            (String::from("HOST"), String::from("localhost")),
+            (String::from("PORT"), String::from("port-5432")),
+        ];
+        assert_eq!(
+            Config::new()
+                .transform_value("port", |raw| raw.trim_start_matches("port-").to_owned())
+                .from_iter::<_, Database>(data),
+            Ok(Database {
+                host: String::from("localhost"),
+                port: 5432
+            })
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn deserializes_json_array_sequences() {
+        let config = Config::new().json_arrays(true);
+        let data = vec![(String::from("TAGS"), String::from(r#"["a", "b,c"]"#))];
+        assert_eq!(
+            config.from_iter::<_, Tags>(data).map(|tags| tags.tags),
+            Ok(vec![String::from("a"), String::from("b,c")])
+        );
+        let data = vec![(String::from("TAGS"), String::from("a, b"))];
+        assert_eq!(
+            config.from_iter::<_, Tags>(data).map(|tags| tags.tags),
+            Ok(vec![String::from("a"), String::from("b")])
+        );
+    }
+
+    #[test]
+    fn validates_without_returning_values() {
+        let data = vec![(String::from("HOST"), String::from("localhost"))];
+        assert_eq!(
+            validate_iter::<_, Database>(data),
+            Err(Error::MissingValue(String::from("PORT")))
+        );
+        let data = vec![
+            (String::from("HOST"), String::from("localhost")),
+            (String::from("PORT"), String::from("5432")),
+        ];
+        assert_eq!(
+            Config::new()
+                .prefix("APP_")
+                .validate_iter::<_, Database>(data.clone()),
+            Err(Error::MissingValue(String::from("APP_HOST")))
+        );
+        assert_eq!(validate_iter::<_, Database>(data), Ok(()));
+    }
+
+    #[test]
+    fn rejects_over_long_values() {
+        let config = Config::new().max_value_len(9);
+        let data = vec![
+            (String::from("HOST"), String::from("localhost.localdomain")),
+            (String::from("PORT"), String::from("5432")),
+        ];
+        assert_eq!(
+            config.from_iter::<_, Database>(data),
+            Err(Error::Custom(String::from(
+                "value provided by HOST exceeds the maximum length of 9 bytes"
+            )))
+        );
+        let data = vec![
+            (String::from("HOST"), String::from("localhost")),
+            (String::from("PORT"), String::from("5432")),
+        ];
+        assert_eq!(
+            config.from_iter::<_, Database>(data),
+            Ok(Database {
+                host: String::from("localhost"),
+                port: 5432
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_control_chars() {
+        let config = Config::new().reject_control_chars(true);
+        let data = vec![
+            (String::from("HOST"), String::from("local\0host")),
+            (String::from("PORT"), String::from("5432")),
+        ];
+        assert_eq!(
+            config.from_iter::<_, Database>(data),
+            Err(Error::Custom(String::from(
+                "value provided by HOST contains control characters"
+            )))
+        );
+        let data = vec![
+            (String::from("HOST"), String::from("localhost")),
+            (String::from("PORT"), String::from("5432")),
+        ];
+        assert_eq!(
+            config.from_iter::<_, Database>(data),
+            Ok(Database {
+                host: String::from("localhost"),
+                port: 5432
+            })
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Tls {
+        tls_enabled: bool,
+        tls_verify: Option<bool>,
+    }
+
+    #[test]
+    fn applies_dependent_defaults() {
+        let config = Config::new().with_dependent_defaults(|tls: &mut Tls| {
+            tls.tls_verify.get_or_insert(tls.tls_enabled);
+        });
+        let data = vec![(String::from("TLS_ENABLED"), String::from("true"))];
+        assert_eq!(
+            config.from_iter(data),
+            Ok(Tls {
+                tls_enabled: true,
+                tls_verify: Some(true)
+            })
+        );
+        let data = vec![
+            (String::from("TLS_ENABLED"), String::from("true")),
+            (String::from("TLS_VERIFY"), String::from("false")),
+        ];
+        assert_eq!(
+            config.from_iter(data),
+            Ok(Tls {
+                tls_enabled: true,
+                tls_verify: Some(false)
+            })
+        );
+    }
+
+    #[test]
+    fn fails_with_missing_required_option() {
+        let config = Config::new().prefix("APP_").require(["timeout"]);
+        let data = vec![(String::from("APP_NAME"), String::from("demo"))];
+        assert_eq!(
+            config.from_iter::<_, Nullable>(data),
+            Err(Error::MissingValue(String::from("APP_TIMEOUT")))
+        );
+        let data = vec![
+            (String::from("APP_NAME"), String::from("demo")),
+            (String::from("APP_TIMEOUT"), String::from("30")),
+        ];
+        assert_eq!(
+            config.from_iter::<_, Nullable>(data),
+            Ok(Nullable {
+                timeout: Some(30),
+                label: None,
+                name: String::from("demo")
+            })
+        );
+    }
+
+    #[test]
+    fn required_fields_may_be_resolved() {
+        let config = Config::new()
+            .prefix("APP_")
+            .require(["timeout"])
+            .require(["label"])
+            .fallback_source(|var: &str| match var {
+                "APP_TIMEOUT" => Some(String::from("30")),
+                _ => None,
+            });
+        let data = vec![
+            (String::from("APP_NAME"), String::from("demo")),
+            (String::from("APP_LABEL"), String::from("edge")),
+        ];
+        assert_eq!(
+            config.from_iter::<_, Nullable>(data),
+            Ok(Nullable {
+                timeout: Some(30),
+                label: Some(String::from("edge")),
+                name: String::from("demo")
+            })
+        );
+        let data = vec![(String::from("APP_NAME"), String::from("demo"))];
+        assert_eq!(
+            config.from_iter::<_, Nullable>(data),
+            Err(Error::MissingValue(String::from("APP_LABEL")))
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(transparent)]
+    pub struct TransparentDatabase(Database);
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct WrappedDatabase(Database);
+
+    #[test]
+    fn deserializes_newtype_wrappers() {
+        let data = vec![
+            (String::from("HOST"), String::from("localhost")),
+            (String::from("PORT"), String::from("5432")),
+        ];
+        let database = Database {
+            host: String::from("localhost"),
+            port: 5432,
+        };
+        assert_eq!(
+            from_iter::<_, TransparentDatabase>(data.clone()),
+            Ok(TransparentDatabase(database.clone()))
+        );
+        assert_eq!(
+            from_iter::<_, WrappedDatabase>(data),
+            Ok(WrappedDatabase(database))
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Certs {
+        #[serde(default)]
+        tls_disabled: bool,
+        cert_path: Option<String>,
+    }
+
+    #[test]
+    fn requires_fields_unless_condition_holds() {
+        let config = Config::new().required_unless("cert_path", "tls_disabled");
+        let data = vec![(String::from("TLS_DISABLED"), String::from("true"))];
+        assert_eq!(
+            config.from_iter::<_, Certs>(data),
+            Ok(Certs {
+                tls_disabled: true,
+                cert_path: None
+            })
+        );
+        for data in [
+            vec![(String::from("TLS_DISABLED"), String::from("false"))],
+            vec![],
+        ] {
+            assert_eq!(
+                config.from_iter::<_, Certs>(data),
+                Err(Error::MissingValue(String::from("CERT_PATH")))
+            );
+        }
+        let data = vec![(String::from("CERT_PATH"), String::from("/etc/cert.pem"))];
+        assert_eq!(
+            config.from_iter::<_, Certs>(data),
+            Ok(Certs {
+                tls_disabled: false,
+                cert_path: Some(String::from("/etc/cert.pem"))
+            })
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Workspace {
+        home: Option<String>,
+        path: Option<String>,
+    }
+
+    #[test]
+    fn ignores_blocked_vars() {
+        let data = vec![
+            (String::from("HOME"), String::from("/root")),
+            (String::from("PATH"), String::from("/bin")),
+        ];
+        assert_eq!(
+            Config::new()
+                .block(["home"])
+                .from_iter::<_, Workspace>(data),
+            Ok(Workspace {
+                home: None,
+                path: Some(String::from("/bin"))
+            })
+        );
+    }
+
+    #[test]
+    fn reports_warnings() {
+        let data = vec![
+            (String::from("APP_NAME"), String::from("demo")),
+            (String::from("APP_TTL"), String::from("30")),
+        ];
+        assert_eq!(
+            Config::new()
+                .prefix("APP_")
+                .deprecated_var("TTL", "timeout")
+                .from_iter_with_warnings::<_, Nullable>(data),
+            Ok((
+                Nullable {
+                    timeout: Some(30),
+                    label: None,
+                    name: String::from("demo")
+                },
+                vec![Warning::DeprecatedVar {
+                    var: String::from("APP_TTL"),
+                    replacement: String::from("APP_TIMEOUT")
+                }]
+            ))
+        );
+        let data = vec![
+            (String::from("APP_NAME"), String::from("edge")),
+            (String::from("APP_UPSTREAM__HOST"), String::from("backend")),
+        ];
+        assert_eq!(
+            Config::new()
+                .prefix("APP_")
+                .nested(true)
+                .from_iter_with_warnings::<_, Gateway>(data)
+                .map(|(_, warnings)| warnings),
+            Ok(vec![Warning::Defaulted {
+                var: String::from("APP_UPSTREAM__PORT")
+            }])
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Upstream {
+        host: String,
+        #[serde(default)]
+        port: u16,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Gateway {
+        name: String,
+        upstream: Upstream,
+        label: Option<String>,
+    }
+
+    #[test]
+    fn reports_provenance() {
+        let config = Config::new()
+            .prefix("APP_")
+            .resolve(|field| (field == "timeout").then(|| String::from("30")));
+        let data = vec![(String::from("APP_NAME"), String::from("demo"))];
+        let (_, provenance) = config
+            .from_iter_with_provenance::<_, Nullable>(data)
+            .unwrap();
+        assert_eq!(
+            provenance,
+            HashMap::from([
+                (
+                    String::from("timeout"),
+                    Provenance::Fallback(String::from("APP_TIMEOUT"))
+                ),
+                (String::from("label"), Provenance::Default),
+                (
+                    String::from("name"),
+                    Provenance::Env(String::from("APP_NAME"))
+                ),
+            ])
+        );
+
+        let path = env::temp_dir().join(format!("envy-provenance-{}.env", std::process::id()));
+        fs::write(&path, "APP_NAME=demo\n").expect("failed to write .env");
+        let result = config.from_dotenv_files_with_provenance::<Nullable, _>(&[&path]);
+        fs::remove_file(&path).expect("failed to remove .env");
+        assert_eq!(
+            result.unwrap().1["name"],
+            Provenance::File {
+                var: String::from("APP_NAME"),
+                path
+            }
+        );
+
+        let data = vec![
+            (String::from("APP_NAME"), String::from("edge")),
+            (String::from("APP_UPSTREAM__HOST"), String::from("backend")),
+        ];
+        let (_, provenance) = Config::new()
+            .prefix("APP_")
+            .nested(true)
+            .from_iter_with_provenance::<_, Gateway>(data)
+            .unwrap();
+        assert_eq!(
+            provenance,
+            HashMap::from([
+                (
+                    String::from("name"),
+                    Provenance::Env(String::from("APP_NAME"))
+                ),
+                (
+                    String::from("upstream__host"),
+                    Provenance::Env(String::from("APP_UPSTREAM__HOST"))
+                ),
+                (String::from("upstream__port"), Provenance::Default),
+                (String::from("label"), Provenance::Default),
+            ])
+        );
+    }
+
+    #[test]
+    fn treats_empty_values_as_absent() {
+        let data = vec![
+            (String::from("NAME"), String::from("demo")),
+            (String::from("TIMEOUT"), String::from("")),
+            (String::from("LABEL"), String::from("")),
+        ];
+        assert_eq!(
+            Config::new()
+                .empty_as_absent(true)
+                .from_iter::<_, Nullable>(data.clone()),
+            Ok(Nullable {
+                timeout: None,
+                label: None,
+                name: String::from("demo")
+            })
+        );
+        assert!(from_iter::<_, Nullable>(data).is_err());
+        let data = vec![(String::from("NAME"), String::from(""))];
+        assert_eq!(
+            Config::new()
+                .empty_as_absent(true)
+                .from_iter::<_, Nullable>(data),
+            Err(Error::MissingValue(String::from("NAME")))
+        );
+    }
+
+    #[test]
+    fn discards_invalid_optional_values() {
+        let config = Config::new().prefix("APP_").lenient_optional(true);
+        let data = vec![
+            (String::from("APP_NAME"), String::from("demo")),
+            (String::from("APP_TIMEOUT"), String::from("soon")),
+            (String::from("APP_LABEL"), String::from("web")),
+        ];
+        assert_eq!(
+            config.from_iter_with_warnings::<_, Nullable>(data),
+            Ok((
+                Nullable {
+                    timeout: None,
+                    label: Some(String::from("web")),
+                    name: String::from("demo")
+                },
+                vec![Warning::Discarded {
+                        var: String::from("APP_TIMEOUT"),
+                        message: String::from(
+                            "invalid digit found in string while parsing value 'soon' provided by TIMEOUT"
+                        )
+                    }
+                ]
+            ))
+        );
+        let data = vec![
+            (String::from("APP_NAME"), String::from("demo")),
+            (String::from("APP_TIMEOUT"), String::from("30")),
+        ];
+        assert_eq!(
+            config.from_iter::<_, Nullable>(data),
+            Ok(Nullable {
+                timeout: Some(30),
+                label: None,
+                name: String::from("demo")
+            })
+        );
+        let data = vec![
+            (String::from("APP_HOST"), String::from("localhost")),
+            (String::from("APP_PORT"), String::from("abc")),
+        ];
+        assert!(config.from_iter::<_, Database>(data).is_err());
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Plugin {
+        name: String,
+        config: Option<String>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Plugins {
+        plugin: Vec<Plugin>,
+        plugin_dir: String,
+    }
+
+    #[test]
+    fn deserializes_separated_indexed_groups() {
+        let data = vec![
+            (String::from("PLUGIN_1_NAME"), String::from("metrics")),
+            (String::from("PLUGIN_0_NAME"), String::from("auth")),
+            (String::from("PLUGIN_0_CONFIG"), String::from("strict")),
+            (String::from("PLUGIN_DIR"), String::from("/plugins")),
+        ];
+        assert_eq!(
+            Config::new()
+                .indexed_groups("_")
+                .from_iter::<_, Plugins>(data),
+            Ok(Plugins {
+                plugin: vec![
+                    Plugin {
+                        name: String::from("auth"),
+                        config: Some(String::from("strict"))
+                    },
+                    Plugin {
+                        name: String::from("metrics"),
+                        config: None
+                    }
+                ],
+                plugin_dir: String::from("/plugins")
+            })
+        );
+    }
+
+    #[test]
+    fn fails_with_missing_field_in_separated_group() {
+        let data = vec![(String::from("PLUGIN_0_CONFIG"), String::from("strict"))];
+        assert_eq!(
+            Config::new()
+                .indexed_groups("_")
+                .from_iter::<_, Plugins>(data),
+            Err(Error::MissingValue(String::from("PLUGIN_0_NAME")))
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Feature {
+        #[serde(default)]
+        feature: bool,
+    }
+
+    #[test]
+    fn deserializes_empty_bools_as_true() {
+        let config = Config::new().empty_bool_true(true);
+        for (value, expected) in [
+            (Some(""), true),
+            (Some("false"), false),
+            (Some("true"), true),
+            (None, false),
+        ] {
+            let data = value.map(|value| (String::from("FEATURE"), String::from(value)));
+            assert_eq!(
+                config.from_iter::<_, Feature>(data),
+                Ok(Feature { feature: expected })
+            );
+        }
+    }
+
+    #[test]
+    fn queries_fallback_source_for_absent_vars() {
+        let queried = Arc::new(Mutex::new(Vec::new()));
+        let log = queried.clone();
+        let config = Config::new()
+            .prefix("APP_")
+            .fallback_source(move |var: &str| {
+                log.lock().unwrap().push(var.to_owned());
+                Some(String::from("5432"))
+            });
+        let data = vec![(String::from("APP_HOST"), String::from("localhost"))];
+        assert_eq!(
+            config.from_iter::<_, Database>(data),
+            Ok(Database {
+                host: String::from("localhost"),
+                port: 5432
+            })
+        );
+        assert_eq!(*queried.lock().unwrap(), vec![String::from("APP_PORT")]);
+    }
+
+    #[test]
+    fn deserializes_sequences_with_separator() {
+        let config = Config::new().separator(':');
+        let data = vec![(String::from("TAGS"), String::from("a:b, c:d"))];
+        assert_eq!(
+            config.from_iter::<_, Tags>(data).map(|tags| tags.tags),
+            Ok(vec![
+                String::from("a"),
+                String::from("b, c"),
+                String::from("d")
+            ])
+        );
+        let data = vec![(String::from("TAGS"), String::new())];
+        assert_eq!(
+            config.from_iter::<_, Tags>(data).map(|tags| tags.tags),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn detects_separators() {
+        let config = Config::new().auto_separator();
+        let from = |value: &str| {
+            config.from_iter::<_, Foo>(vec![
+                (String::from("BAR"), String::from("test")),
+                (String::from("BAZ"), String::from("true")),
+                (String::from("DOOM"), String::from("1,2")),
+                (String::from("BOOM"), value.to_owned()),
+                (String::from("NEWTYPE"), String::from("42")),
+            ])
+        };
+        assert_eq!(from("a;b;c").unwrap().boom, vec!["a", "b", "c"]);
+        assert_eq!(from("a, b").unwrap().boom, vec!["a", "b"]);
+        assert_eq!(from("a\\;b|c").unwrap().boom, vec!["a\\;b", "c"]);
+        assert_eq!(
+            from("a,b;c"),
+            Err(Error::InvalidValue {
+                var: String::from("BOOM"),
+                value: String::from("a,b;c"),
+                code: ErrorCode::Invalid,
+                message: String::from("ambiguous separator, found both ',' and ';'")
+            })
+        );
+    }
+
+    #[test]
+    fn decodes_percent_encoded_keys() {
+        let data = vec![
+            (String::from("APP%2EDB%2EHOST"), String::from("localhost")),
+            (String::from("APP%2EDB%2EPORT"), String::from("5432")),
+        ];
+        assert_eq!(
+            Config::new()
+                .prefix("APP.")
+                .nested(true)
+                .nesting_separator(".")
+                .percent_decode_keys(true)
+                .from_iter::<_, App>(data),
+            Ok(App {
+                db: Database {
+                    host: String::from("localhost"),
+                    port: 5432
+                }
+            })
+        );
+        let config = Config::new().percent_decode_keys(true);
+        assert_eq!(
+            config.decode_key(String::from("A%2d%zz%+1B%")),
+            "A-%zz%+1B%"
+        );
+        assert_eq!(config.decode_key(String::from("A%FF")), "A%FF");
+    }
+
+    #[test]
+    fn strips_chars_from_keys() {
+        let data = vec![
+            (String::from("CFG:HOST"), String::from("localhost")),
+            (String::from("CFG:PORT"), String::from("5432")),
+        ];
+        assert_eq!(
+            Config::new()
+                .prefix("CFG")
+                .strip_chars_from_keys(&[':'])
+                .from_iter::<_, Database>(data),
+            Ok(Database {
+                host: String::from("localhost"),
+                port: 5432
+            })
+        );
+        let config = Config::new()
+            .percent_decode_keys(true)
+            .strip_chars_from_keys(&[':', '-']);
+        assert_eq!(config.decode_key(String::from("A%3AB-C")), "ABC");
+    }
+
+    #[test]
+    fn denies_unknown_vars() {
+        let config = Config::new().prefix("APP_").deny_unknown();
+        let data = vec![
+            (String::from("APP_HOST"), String::from("localhost")),
+            (String::from("APP_PORT"), String::from("5432")),
+            (String::from("OTHER"), String::from("ignored")),
+        ];
+        assert_eq!(
+            config.from_iter::<_, Database>(data),
+            Ok(Database {
+                host: String::from("localhost"),
+                port: 5432
+            })
+        );
+        let data = vec![
+            (String::from("APP_HOST"), String::from("localhost")),
+            (String::from("APP_PORT"), String::from("5432")),
+            (String::from("APP_HOTS"), String::from("typo")),
+        ];
+        assert_eq!(
+            config.from_iter::<_, Database>(data),
+            Err(Error::UnknownField(String::from("APP_HOTS")))
+        );
+        let data = vec![
+            (String::from("DB__HOST"), String::from("localhost")),
+            (String::from("DB__PORT"), String::from("5432")),
+            (String::from("DB__PROT"), String::from("5433")),
+        ];
+        assert_eq!(
+            Config::new()
+                .nested(true)
+                .deny_unknown()
+                .from_iter::<_, App>(data),
+            Err(Error::UnknownField(String::from("DB__PROT")))
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Deployment {
+        name: String,
+        primary: App,
+    }
+
+    #[test]
+    fn nests_with_custom_separator() {
+        let data = vec![
+            (String::from("NAME"), String::from("main")),
+            (String::from("PRIMARY.DB.HOST"), String::from("localhost")),
+            (String::from("PRIMARY.DB.PORT"), String::from("5432")),
+        ];
+        assert_eq!(
+            Config::new()
+                .nested(true)
+                .nesting_separator(".")
+                .from_iter::<_, Deployment>(data),
+            Ok(Deployment {
+                name: String::from("main"),
+                primary: App {
+                    db: Database {
+                        host: String::from("localhost"),
+                        port: 5432
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn fails_with_nesting_deeper_than_max_depth() {
+        let config = Config::new().nested(true).max_depth(1);
+        let data = vec![
+            (String::from("DB__HOST"), String::from("localhost")),
+            (String::from("DB__PORT"), String::from("5432")),
+        ];
+        assert_eq!(
+            config.from_iter::<_, App>(data),
+            Ok(App {
+                db: Database {
+                    host: String::from("localhost"),
+                    port: 5432
+                }
+            })
+        );
+        let data = vec![
+            (String::from("DB__HOST"), String::from("localhost")),
+            (String::from("DB__PORT__NUMBER"), String::from("5432")),
+        ];
+        assert_eq!(
+            config.from_iter::<_, App>(data),
+            Err(Error::Custom(String::from(
+                "DB__PORT__NUMBER exceeds the maximum nesting depth of 1"
+            )))
+        );
+    }
+
+    #[test]
+    fn deserializes_escaped_separators() {
+        for (value, expected) in [
+            (r"a\,b,c", vec!["a,b", "c"]),
+            (r"a,b\,", vec!["a", "b,"]),
+            (r"a\\,b", vec![r"a\", "b"]),
+            (r"C:\dir,x", vec![r"C:\dir", "x"]),
+            ("a,,b", vec!["a", "", "b"]),
+        ] {
+            let data = vec![(String::from("TAGS"), String::from(value))];
+            assert_eq!(
+                from_iter::<_, Tags>(data).map(|tags| tags.tags),
+                Ok(expected.into_iter().map(String::from).collect())
+            );
+        }
+    }
+
+    #[test]
+    fn unquotes_values_except_for_excluded_fields() {
+        let data = vec![
+            (String::from("HOST"), String::from("'localhost'")),
+            (String::from("PASSWORD"), String::from("'secret'")),
+            (String::from("LABELS__A"), String::from("\"1\"")),
+        ];
+        assert_eq!(
+            Config::new()
+                .nested(true)
+                .unquote(true)
+                .no_unquote(["password"])
+                .from_iter::<_, Fingerprinted>(data),
+            Ok(Fingerprinted {
+                host: String::from("localhost"),
+                password: String::from("'secret'"),
+                labels: HashMap::from([(String::from("a"), String::from("1"))])
+            })
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Service {
+        name: String,
+        region: String,
+        zones: Vec<String>,
+    }
+
+    #[test]
+    fn fills_soft_missing_fields() {
+        let data = vec![(String::from("NAME"), String::from("api"))];
+        assert_eq!(
+            Config::new()
+                .soft_missing(["REGION", "zones"])
+                .from_iter_with_missing::<_, Service>(data.clone()),
+            Ok((
+                Service {
+                    name: String::from("api"),
+                    region: String::new(),
+                    zones: Vec::new()
+                },
+                vec!["region", "zones"]
+            ))
+        );
+        assert_eq!(
+            Config::new()
+                .soft_missing(["zones"])
+                .from_iter_with_missing::<_, Service>(data),
+            Err(Error::MissingValue(String::from("REGION")))
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Borrowed<'a> {
+        host: &'a str,
+        #[serde(borrow)]
+        name: Cow<'a, str>,
+        tags: Vec<String>,
+    }
+
+    #[test]
+    fn deserializes_borrowed_values() {
+        let data = [("HOST", "localhost"), ("NAME", "'demo'"), ("TAGS", "a,b")];
+        let borrowed = Config::new()
+            .unquote(true)
+            .from_iter_borrowed::<_, Borrowed>(data)
+            .unwrap();
+        assert_eq!(borrowed.host, "localhost");
+        assert!(matches!(borrowed.name, Cow::Borrowed("demo")));
+        assert_eq!(borrowed.tags, vec!["a", "b"]);
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Listeners {
+        ports: Vec<std::num::NonZeroU16>,
+    }
+
+    #[test]
+    fn validates_non_zero_elements() {
+        let data = vec![(String::from("PORTS"), String::from("80,443"))];
+        assert_eq!(
+            from_iter::<_, Listeners>(data).map(|listeners| listeners.ports),
+            Ok(vec![
+                std::num::NonZeroU16::new(80).unwrap(),
+                std::num::NonZeroU16::new(443).unwrap()
+            ])
+        );
+        let data = vec![(String::from("PORTS"), String::from("80,443,0"))];
+        assert_eq!(
+            from_iter::<_, Listeners>(data),
+            Err(Error::SeqElement {
+                var: String::from("PORTS"),
+                index: 2,
+                value: String::from("0"),
+                code: ErrorCode::Invalid,
+                message: String::from("invalid value: integer `0`, expected a nonzero u16")
+            })
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Required {
+        required_version: String,
+    }
+
+    #[test]
+    fn unquotes_only_matching_pairs() {
+        let version = |config: Config, value: &str| {
+            let data = vec![(String::from("REQUIRED_VERSION"), String::from(value))];
+            config
+                .from_iter::<_, Required>(data)
+                .map(|required| required.required_version)
+        };
+        for (value, expected) in [
+            ("\">=3.11<4\"", ">=3.11<4"),
+            ("'>=3.11<4'", ">=3.11<4"),
+            ("\"'>=3.11<4'\"", "'>=3.11<4'"),
+            ("\">=3.11<4'", "\">=3.11<4'"),
+            (">=\"3.11\"<4", ">=\"3.11\"<4"),
+            ("\"", "\""),
+        ] {
+            assert_eq!(
+                version(Config::new().unquote(true), value),
+                Ok(String::from(expected))
+            );
+        }
+        assert_eq!(
+            version(Config::new(), "\">=3.11<4\""),
+            Ok(String::from("\">=3.11<4\""))
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Labeled {
+        labels: Option<HashMap<String, u32>>,
+    }
+
+    #[test]
+    fn deserializes_optional_maps() {
+        let labels = |value: Option<&str>| {
+            let data = value.map(|value| (String::from("LABELS"), String::from(value)));
+            from_iter::<_, Labeled>(data).map(|labeled| labeled.labels)
+        };
+        assert_eq!(labels(None), Ok(None));
+        assert_eq!(labels(Some("")), Ok(Some(HashMap::new())));
+        assert_eq!(
+            labels(Some("a:1, b:2")),
+            Ok(Some(HashMap::from([
+                (String::from("a"), 1),
+                (String::from("b"), 2)
+            ])))
+        );
+        assert_eq!(
+            labels(Some("a:1,b")),
+            Err(Error::InvalidValue {
+                var: String::from("LABELS"),
+                value: String::from("a:1,b"),
+                code: ErrorCode::Invalid,
+                message: String::from("expected a key:value entry but found 'b'")
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_quoted_numbers_without_unquoting() {
+        let data = vec![
+            (String::from("API_KEY"), String::from("\"hunter2\"")),
+            (String::from("PORT"), String::from("\"8080\"")),
+        ];
+        let error = from_iter::<_, Secrets>(data.clone()).unwrap_err();
+        assert_eq!(
+            error,
+            Error::InvalidValue {
+                var: String::from("PORT"),
+                value: String::from("\"8080\""),
+                code: ErrorCode::InvalidInteger,
+                message: String::from(
+                    "invalid digit found in string (the number is quoted, remove the quotes or \
+                     enable unquoting)"
+                )
+            }
+        );
+        assert_eq!(error.code(), ErrorCode::InvalidInteger);
+        assert_eq!(
+            Config::new().unquote(true).from_iter::<_, Secrets>(data),
+            Ok(Secrets {
+                api_key: String::from("hunter2"),
+                port: 8080
+            })
+        );
+    }
+
+    #[test]
+    fn decrypts_sealed_values() {
+        // a stand-in cipher reversing the plaintext
+        let config = Config::new().decrypt("enc:", |sealed| {
+            sealed
+                .strip_prefix("v1:")
+                .map(|sealed| sealed.chars().rev().collect())
+        });
+        let data = vec![
+            (String::from("API_KEY"), String::from("enc:v1:2retnuh")),
+            (String::from("PORT"), String::from("enc:v1:0808")),
+        ];
+        assert_eq!(
+            config.from_iter::<_, Secrets>(data),
+            Ok(Secrets {
+                api_key: String::from("hunter2"),
+                port: 8080
+            })
+        );
+        let data = vec![
+            (String::from("API_KEY"), String::from("enc:v2:2retnuh")),
+            (String::from("PORT"), String::from("8080")),
+        ];
+        assert_eq!(
+            config.from_iter::<_, Secrets>(data),
+            Err(Error::Custom(String::from(
+                "failed to decrypt value provided by API_KEY"
+            )))
+        );
     }
-}
-
-/// Produces a instance of `KeepNames` for keeping the serde field names
-pub fn keep_names() -> KeepNames {
-    KeepNames {}
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde::Deserialize;
-    use std::collections::HashMap;
 
     #[derive(Deserialize, Debug, PartialEq)]
-    #[serde(rename_all = "lowercase")]
-    #[derive(Default)]
-    pub enum Size {
-        Small,
-        #[default]
-        Medium,
-        Large,
+    pub struct Secrets {
+        api_key: String,
+        port: u16,
     }
 
-    pub fn default_kaboom() -> u16 {
-        8080
+    #[test]
+    fn maps_keys_with_custom_function() {
+        let data = vec![
+            (String::from("MY-VAR"), String::from("mapped")),
+            (String::from("port"), String::from("8080")),
+        ];
+        #[derive(Deserialize, Debug, PartialEq)]
+        pub struct Mapped {
+            my_var: String,
+            port: u16,
+        }
+        assert_eq!(
+            Config::new()
+                .key_map(|name| name.to_lowercase().replace('-', "_"))
+                .from_iter::<_, Mapped>(data),
+            Ok(Mapped {
+                my_var: String::from("mapped"),
+                port: 8080
+            })
+        );
     }
 
     #[derive(Deserialize, Debug, PartialEq)]
-    pub struct CustomNewType(u32);
+    pub struct Shards {
+        shards: HashMap<u32, String>,
+    }
 
-    #[derive(Deserialize, Debug, PartialEq)]
-    pub struct Foo {
-        bar: String,
-        baz: bool,
-        zoom: Option<u16>,
-        doom: Vec<u64>,
-        boom: Vec<String>,
-        #[serde(default = "default_kaboom")]
-        kaboom: u16,
-        #[serde(default)]
-        debug_mode: bool,
-        #[serde(default)]
-        size: Size,
-        provided: Option<String>,
-        newtype: CustomNewType,
+    #[test]
+    fn deserializes_typed_map_keys() {
+        let data = vec![(String::from("SHARDS"), String::from("0:a,1:b,2:c"))];
+        assert_eq!(
+            from_iter::<_, Shards>(data),
+            Ok(Shards {
+                shards: HashMap::from([
+                    (0, String::from("a")),
+                    (1, String::from("b")),
+                    (2, String::from("c"))
+                ])
+            })
+        );
+        let data = vec![(String::from("SHARDS"), String::from("0:a,one:b"))];
+        assert_eq!(
+            from_iter::<_, Shards>(data),
+            Err(Error::InvalidValue {
+                var: String::from("SHARDS"),
+                value: String::from("one"),
+                code: ErrorCode::InvalidInteger,
+                message: String::from("invalid digit found in string")
+            })
+        );
     }
 
-    #[derive(Deserialize, Debug, PartialEq)]
-    pub struct CrazyFoo {
-        #[serde(rename = "BaR")]
-        bar: String,
-        #[serde(rename = "SCREAMING_BAZ")]
-        screaming_baz: bool,
-        zoom: Option<u16>,
+    #[test]
+    fn serializes_shell_exports() {
+        let database = Database {
+            host: String::from("it's a host"),
+            port: 5432,
+        };
+        assert_eq!(
+            to_shell(&database),
+            Ok(String::from(
+                "export HOST='it'\\''s a host'\nexport PORT='5432'\n"
+            ))
+        );
     }
 
     #[test]
-    fn deserialize_from_iter() {
+    fn fails_with_invalid_type() {
         let data = vec![
             (String::from("BAR"), String::from("test")),
-            (String::from("BAZ"), String::from("true")),
-            (String::from("DOOM"), String::from("1, 2, 3 ")),
-            // Empty string should result in empty vector.
-            (String::from("BOOM"), String::from("")),
-            (String::from("SIZE"), String::from("small")),
-            (String::from("PROVIDED"), String::from("test")),
-            (String::from("NEWTYPE"), String::from("42")),
+            (String::from("BAZ"), String::from("notabool")),
+            (String::from("DOOM"), String::from("1,2,3")),
         ];
         match from_iter::<_, Foo>(data) {
-            Ok(actual) => assert_eq!(
-                actual,
-                Foo {
-                    bar: String::from("test"),
-                    baz: true,
-                    zoom: None,
-                    doom: vec![1, 2, 3],
-                    boom: vec![],
-                    kaboom: 8080,
-                    debug_mode: false,
-                    size: Size::Small,
-                    provided: Some(String::from("test")),
-                    newtype: CustomNewType(42)
+            Ok(_) => panic!("expected failure"),
+            Err(e) => assert_eq!(
+                e,
+                Error::InvalidValue {
+                    var: String::from("BAZ"),
+                    value: String::from("notabool"),
+                    code: ErrorCode::InvalidBool,
+                    message: String::from("provided string was not `true` or `false`")
                 }
             ),
-            Err(e) => panic!("{:#?}", e),
         }
     }
 
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Initial {
+        initial: char,
+    }
+
     #[test]
-    fn fails_with_missing_value() {
+    fn fails_with_invalid_char() {
+        let data = vec![(String::from("INITIAL"), String::from("ab"))];
+        assert_eq!(
+            from_iter::<_, Initial>(data).map_err(|e| e.code()),
+            Err(ErrorCode::InvalidChar)
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Mixed {
+        foobar: u16,
+    }
+
+    #[test]
+    fn fails_with_original_var_name() {
+        let data = vec![(String::from("FooBar"), String::from("bad"))];
+        assert_eq!(
+            from_iter::<_, Mixed>(data),
+            Err(Error::InvalidValue {
+                var: String::from("FooBar"),
+                value: String::from("bad"),
+                code: ErrorCode::InvalidInteger,
+                message: String::from("invalid digit found in string")
+            })
+        );
+    }
+
+    #[test]
+    fn fails_with_invalid_seq_element() {
         let data = vec![
             (String::from("BAR"), String::from("test")),
             (String::from("BAZ"), String::from("true")),
+            (String::from("DOOM"), String::from("1,2,three,4")),
         ];
         match from_iter::<_, Foo>(data) {
             Ok(_) => panic!("expected failure"),
-            Err(e) => assert_eq!(e, Error::MissingValue("DOOM".into())),
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "invalid digit found in string while parsing value 'three' provided by element 2 of DOOM"
+                );
+                assert_eq!(
+                    e,
+                    Error::SeqElement {
+                        var: String::from("DOOM"),
+                        index: 2,
+                        value: String::from("three"),
+                        code: ErrorCode::InvalidInteger,
+                        message: String::from("invalid digit found in string")
+                    }
+                );
+            }
         }
     }
 
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Tags {
+        tags: Vec<String>,
+    }
+
     #[test]
-    fn prefixed_fails_with_missing_value() {
-        let data = vec![
-            (String::from("PREFIX_BAR"), String::from("test")),
-            (String::from("PREFIX_BAZ"), String::from("true")),
-        ];
+    fn csv_preserves_quoted_empty_elements() {
+        let data = vec![(
+            String::from("TAGS"),
+            String::from(r#"a,"",b, "c, ""d""" ,,"#),
+        )];
+        assert_eq!(
+            Config::new().csv(true).from_iter::<_, Tags>(data),
+            Ok(Tags {
+                tags: vec![
+                    String::from("a"),
+                    String::new(),
+                    String::from("b"),
+                    String::from(r#"c, "d""#)
+                ]
+            })
+        );
+    }
 
-        match prefixed("PREFIX_").from_iter::<_, Foo>(data) {
-            Ok(_) => panic!("expected failure"),
-            Err(e) => assert_eq!(e, Error::MissingValue("PREFIX_DOOM".into())),
-        }
+    #[test]
+    fn csv_fails_with_unterminated_quote() {
+        let data = vec![(String::from("TAGS"), String::from(r#"a,"b"#))];
+        assert_eq!(
+            Config::new().csv(true).from_iter::<_, Tags>(data),
+            Err(Error::InvalidValue {
+                var: String::from("TAGS"),
+                value: String::from("a,\"b"),
+                code: ErrorCode::Invalid,
+                message: String::from("unterminated quoted element")
+            })
+        );
     }
 
     #[test]
-    fn fails_with_invalid_type() {
-        let data = vec![
-            (String::from("BAR"), String::from("test")),
-            (String::from("BAZ"), String::from("notabool")),
-            (String::from("DOOM"), String::from("1,2,3")),
-        ];
-        match from_iter::<_, Foo>(data) {
-            Ok(_) => panic!("expected failure"),
-            Err(e) => assert_eq!(
-                e,
-                Error::Custom(String::from("provided string was not `true` or `false` while parsing value \'notabool\' provided by BAZ"))
-            ),
-        }
+    fn without_csv_keeps_empty_elements() {
+        let data = vec![(String::from("TAGS"), String::from(r#"a,"",,b"#))];
+        assert_eq!(
+            from_iter::<_, Tags>(data),
+            Ok(Tags {
+                tags: vec![
+                    String::from("a"),
+                    String::from(r#""""#),
+                    String::new(),
+                    String::from("b")
+                ]
+            })
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Cluster {
+        seed_nodes: Vec<std::net::SocketAddr>,
+    }
+
+    #[test]
+    fn deserializes_socket_addrs() {
+        let data = vec![(
+            String::from("SEED_NODES"),
+            String::from("1.2.3.4:7000, 5.6.7.8:7000"),
+        )];
+        assert_eq!(
+            from_iter::<_, Cluster>(data),
+            Ok(Cluster {
+                seed_nodes: vec![
+                    "1.2.3.4:7000".parse().unwrap(),
+                    "5.6.7.8:7000".parse().unwrap()
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn socket_addrs_fail_with_invalid_element() {
+        let data = vec![(
+            String::from("SEED_NODES"),
+            String::from("1.2.3.4:7000,5.6.7.8,9.10.11.12:7000"),
+        )];
+        assert_eq!(
+            from_iter::<_, Cluster>(data),
+            Err(Error::SeqElement {
+                var: String::from("SEED_NODES"),
+                index: 1,
+                value: String::from("5.6.7.8"),
+                code: ErrorCode::Invalid,
+                message: String::from("invalid socket address syntax")
+            })
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct RequiredPort {
+        port: u16,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct OptionalPort {
+        port: Option<u16>,
+    }
+
+    #[test]
+    fn optional_fields_fail_with_detailed_errors() {
+        let data = vec![(String::from("PORT"), String::from("80a"))];
+        let expected = Error::InvalidValue {
+            var: String::from("PORT"),
+            value: String::from("80a"),
+            code: ErrorCode::InvalidInteger,
+            message: String::from("invalid digit found in string"),
+        };
+        assert_eq!(
+            from_iter::<_, RequiredPort>(data.clone()),
+            Err(expected.clone())
+        );
+        assert_eq!(from_iter::<_, OptionalPort>(data), Err(expected));
     }
 
     #[test]
@@ -596,6 +6847,147 @@ mod tests {
         );
     }
 
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Deployed {
+        db_host: String,
+        replicas: u8,
+    }
+
+    #[test]
+    fn section_reads_only_its_vars() {
+        let data = vec![
+            (String::from("PROD_DB_HOST"), String::from("db.prod")),
+            (String::from("PROD_REPLICAS"), String::from("3")),
+            (String::from("STAGING_DB_HOST"), String::from("db.staging")),
+            (String::from("STAGING_REPLICAS"), String::from("1")),
+        ];
+        assert_eq!(
+            section("prod").from_iter::<_, Deployed>(data.clone()),
+            Ok(Deployed {
+                db_host: String::from("db.prod"),
+                replicas: 3
+            })
+        );
+        assert_eq!(
+            section("STAGING").strict().from_iter::<_, Deployed>(data),
+            Ok(Deployed {
+                db_host: String::from("db.staging"),
+                replicas: 1
+            })
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Dynamic {
+        name: String,
+        limits: HashMap<String, u32>,
+    }
+
+    #[test]
+    fn collects_remaining_vars() {
+        let data = vec![
+            (String::from("APP_NAME"), String::from("demo")),
+            (String::from("APP_FOO"), String::from("1")),
+            (String::from("APP_BAR"), String::from("2")),
+            (String::from("OTHER"), String::from("3")),
+        ];
+        assert_eq!(
+            prefixed("APP_")
+                .collect_remaining("limits")
+                .strict()
+                .from_iter::<_, Dynamic>(data),
+            Ok(Dynamic {
+                name: String::from("demo"),
+                limits: HashMap::from([(String::from("foo"), 1), (String::from("bar"), 2)])
+            })
+        );
+        let data = vec![(String::from("APP_NAME"), String::from("demo"))];
+        assert_eq!(
+            prefixed("APP_")
+                .collect_remaining("limits")
+                .from_iter::<_, Dynamic>(data),
+            Ok(Dynamic {
+                name: String::from("demo"),
+                limits: HashMap::new()
+            })
+        );
+    }
+
+    #[test]
+    fn prefixed_any_prefers_earlier_prefixes() {
+        let data = vec![
+            (String::from("OLD_HOST"), String::from("old.example.com")),
+            (String::from("NEW_HOST"), String::from("new.example.com")),
+            (String::from("OLD_PORT"), String::from("5432")),
+        ];
+        assert_eq!(
+            prefixed_any(["NEW_", "OLD_"]).from_iter::<_, Database>(data),
+            Ok(Database {
+                host: String::from("new.example.com"),
+                port: 5432
+            })
+        );
+        let data = vec![(String::from("OLD_HOST"), String::from("old.example.com"))];
+        assert_eq!(
+            prefixed_any(["NEW_", "OLD_"]).from_iter::<_, Database>(data),
+            Err(Error::MissingValue(String::from("NEW_PORT")))
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub enum Auth {
+        Anonymous,
+        Token(String),
+        Basic { user: String, password: String },
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Client {
+        name: String,
+        auth: Auth,
+    }
+
+    #[test]
+    fn selects_enum_variants_with_method_var() {
+        let client = |vars: &[(&str, &str)]| {
+            let vars = vars.iter().map(|(k, v)| (k.to_string(), v.to_string()));
+            Config::new()
+                .nested(true)
+                .nesting_separator("_")
+                .enum_method_var("method")
+                .from_iter::<_, Client>(vars)
+                .map(|client| client.auth)
+        };
+        assert_eq!(
+            client(&[
+                ("NAME", "api"),
+                ("AUTH_METHOD", "token"),
+                ("AUTH_TOKEN", "abc")
+            ]),
+            Ok(Auth::Token(String::from("abc")))
+        );
+        assert_eq!(
+            client(&[
+                ("NAME", "api"),
+                ("AUTH_METHOD", "basic"),
+                ("AUTH_USER", "admin"),
+                ("AUTH_PASSWORD", "hunter2")
+            ]),
+            Ok(Auth::Basic {
+                user: String::from("admin"),
+                password: String::from("hunter2")
+            })
+        );
+        assert_eq!(
+            client(&[("NAME", "api"), ("AUTH_METHOD", "anonymous")]),
+            Ok(Auth::Anonymous)
+        );
+        assert_eq!(
+            client(&[("NAME", "api"), ("AUTH_METHOD", "token")]),
+            Err(Error::MissingValue(String::from("AUTH_TOKEN")))
+        );
+    }
+
     #[test]
     fn prefixed_doesnt_parse_non_prefixed() {
         let mut expected = HashMap::new();
@@ -609,6 +7001,99 @@ mod tests {
         );
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn adapts_os_vars() {
+        use std::os::unix::ffi::OsStringExt;
+        let vars = || {
+            vec![
+                (OsString::from("HOST"), OsString::from("localhost")),
+                (OsString::from("PORT"), OsString::from("5432")),
+                (
+                    OsString::from("NAME"),
+                    OsString::from_vec(b"caf\xe9".to_vec()),
+                ),
+            ]
+        };
+        assert_eq!(
+            utf8_vars(vars(), false).collect::<Vec<_>>(),
+            vec![
+                (String::from("HOST"), String::from("localhost")),
+                (String::from("PORT"), String::from("5432")),
+            ]
+        );
+        assert_eq!(
+            utf8_vars(vars(), true).last(),
+            Some((String::from("NAME"), String::from("caf\u{fffd}")))
+        );
+    }
+
+    #[test]
+    fn prefixed_strict_allows_extra_vars() {
+        let strict = || prefixed("APP_").strict().allow_extra(["APP_DEBUG_TRACE"]);
+        let data = vec![
+            (String::from("APP_HOST"), String::from("localhost")),
+            (String::from("APP_PORT"), String::from("5432")),
+            (String::from("APP_DEBUG_TRACE"), String::from("1")),
+        ];
+        assert_eq!(
+            strict().from_iter::<_, Database>(data),
+            Ok(Database {
+                host: String::from("localhost"),
+                port: 5432
+            })
+        );
+        let data = vec![
+            (String::from("APP_HOST"), String::from("localhost")),
+            (String::from("APP_PORT"), String::from("5432")),
+            (String::from("APP_DEBUG_TRACE"), String::from("1")),
+            (String::from("APP_POTR"), String::from("5433")),
+        ];
+        assert_eq!(
+            strict().from_iter::<_, Database>(data),
+            Err(Error::UnknownField(String::from("APP_POTR")))
+        );
+    }
+
+    #[test]
+    fn prefixed_requires_separator() {
+        let mut expected = HashMap::new();
+        expected.insert("host".to_string(), "localhost".to_string());
+        for prefix in ["APP", "APP_"] {
+            assert_eq!(
+                prefixed(prefix).require_separator('_').from_iter(vec![
+                    ("APP_HOST".to_string(), "localhost".to_string()),
+                    ("APPLE_HOST".to_string(), "orchard".to_string())
+                ]),
+                Ok(expected.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn suffixed_strips_suffixes() {
+        let mut expected = HashMap::new();
+        expected.insert("foo".to_string(), "bar".to_string());
+        expected.insert("".to_string(), "empty".to_string());
+        assert_eq!(
+            suffixed("_URL").from_iter(vec![
+                ("FOO_URL".to_string(), "bar".to_string()),
+                ("_URL".to_string(), "empty".to_string()),
+                ("FOO".to_string(), "ignored".to_string())
+            ]),
+            Ok(expected)
+        );
+    }
+
+    #[test]
+    fn suffixed_reports_missing_suffixed_vars() {
+        assert_eq!(
+            suffixed("_URL")
+                .from_iter::<_, Database>(vec![("HOST_URL".to_string(), "localhost".to_string())]),
+            Err(Error::MissingValue(String::from("PORT_URL")))
+        );
+    }
+
     #[test]
     fn keep_names_from_iter() {
         let data = vec![
@@ -628,4 +7113,16 @@ mod tests {
             Err(e) => panic!("{:#?}", e),
         }
     }
+
+    #[test]
+    fn deserializer_reports_error_path() {
+        let data = vec![
+            (String::from("HOST"), String::from("localhost")),
+            (String::from("PORT"), String::from("nope")),
+        ];
+        let err =
+            serde_path_to_error::deserialize::<_, Database>(Deserializer::new(data.into_iter()))
+                .unwrap_err();
+        assert_eq!(err.path().to_string(), "port");
+    }
 }
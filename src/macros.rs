@@ -0,0 +1,62 @@
+/// Defines a config struct whose fields are documented with the env vars they are read from
+///
+/// Each field is followed by `=>` and a description of its env var. The struct derives
+/// `serde::Deserialize`, so the calling crate must depend on serde with its `derive` feature,
+/// and gains a `help()` function listing each env var with its description.
+///
+/// Env var names in the help text are the uppercased field names and do not account for
+/// serde renames.
+///
+/// # Example
+///
+/// ```
+/// envy::config! {
+///     #[derive(Debug)]
+///     pub struct Config {
+///         port: u16 => "The port to listen on",
+///         #[serde(default)]
+///         debug: bool => "Enables debug logging",
+///     }
+/// }
+///
+/// assert_eq!(
+///     Config::help(),
+///     "PORT\tThe port to listen on\nDEBUG\tEnables debug logging\n"
+/// );
+/// ```
+#[macro_export]
+macro_rules! config {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_attr:meta])*
+                $field_vis:vis $field:ident : $ty:ty => $doc:literal
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(::serde::Deserialize)]
+        $vis struct $name {
+            $(
+                #[doc = $doc]
+                $(#[$field_attr])*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        impl $name {
+            /// Lists the env vars read by this type along with their descriptions
+            pub fn help() -> ::std::string::String {
+                let mut help = ::std::string::String::new();
+                $(
+                    help.push_str(&stringify!($field).to_uppercase());
+                    help.push('\t');
+                    help.push_str($doc);
+                    help.push('\n');
+                )*
+                help
+            }
+        }
+    };
+}
@@ -0,0 +1,94 @@
+//! Validation of deserialized values against a JSON schema
+use crate::{Config, Error, Result};
+use jsonschema::JSONSchema;
+use serde::{de, Serialize};
+use std::env;
+
+impl Config {
+    /// Deserializes a type based on env variables, then validates it against a JSON schema
+    ///
+    /// See [from_iter_validated](#method.from_iter_validated) for details
+    pub fn from_env_validated<T>(
+        &self,
+        schema: &serde_json::Value,
+    ) -> Result<T>
+    where
+        T: de::DeserializeOwned + Serialize,
+    {
+        self.from_iter_validated(env::vars(), schema)
+    }
+
+    /// Deserializes a type based on (String, String) tuples, then validates it against a
+    /// JSON schema
+    ///
+    /// The deserialized value is serialized as JSON for validation, so the schema describes
+    /// typed values, like numbers, rather than the strings provided by env vars. All
+    /// violations of the schema are reported in a single error.
+    pub fn from_iter_validated<Iter, T>(
+        &self,
+        iter: Iter,
+        schema: &serde_json::Value,
+    ) -> Result<T>
+    where
+        T: de::DeserializeOwned + Serialize,
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        let schema = JSONSchema::compile(schema)
+            .map_err(|e| Error::Custom(format!("invalid schema: {}", e)))?;
+        let value = self.from_iter(iter)?;
+        let instance = serde_json::to_value(&value).map_err(|e| Error::Custom(e.to_string()))?;
+        if let Err(errors) = schema.validate(&instance) {
+            let violations = errors
+                .map(|e| format!("{}: {}", e.instance_path, e))
+                .collect::<Vec<_>>();
+            return Err(Error::Custom(format!(
+                "config violates schema: {}",
+                violations.join("; ")
+            )));
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    pub struct Server {
+        host: String,
+        port: u32,
+    }
+
+    #[test]
+    fn validates_against_schema() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "port": { "type": "integer", "maximum": 65535 }
+            }
+        });
+        let data = vec![
+            (String::from("HOST"), String::from("localhost")),
+            (String::from("PORT"), String::from("8080")),
+        ];
+        assert_eq!(
+            Config::new().from_iter_validated::<_, Server>(data, &schema),
+            Ok(Server {
+                host: String::from("localhost"),
+                port: 8080
+            })
+        );
+        let data = vec![
+            (String::from("HOST"), String::from("localhost")),
+            (String::from("PORT"), String::from("70000")),
+        ];
+        assert_eq!(
+            Config::new().from_iter_validated::<_, Server>(data, &schema),
+            Err(Error::Custom(String::from(
+                "config violates schema: /port: 70000 is greater than the maximum of 65535"
+            )))
+        );
+    }
+}
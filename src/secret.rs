@@ -0,0 +1,163 @@
+//! A wrapper keeping secret values out of logs
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{cell::Cell, fmt, ops::Deref};
+
+thread_local! {
+    /// Whether secrets serialize their values, rather than `***`
+    static EXPOSED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `f` with secrets serializing their values, as when capturing a default value
+/// to fill missing fields with
+pub(crate) fn exposed<R>(f: impl FnOnce() -> R) -> R {
+    struct Reset(bool);
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            EXPOSED.with(|exposed| exposed.set(self.0));
+        }
+    }
+    let _reset = Reset(EXPOSED.with(|exposed| exposed.replace(true)));
+    f()
+}
+
+/// A value which is never revealed when serialized or debug formatted
+///
+/// Secrets deserialize like the value they wrap, but serialize to `***`, so
+/// [to_vec_redacted](fn.to_vec_redacted.html) dumps `API_KEY=***` and still shows which
+/// secrets are set. Fields marked `#[serde(skip_serializing)]` are omitted from dumps
+/// entirely.
+///
+/// # Example
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize, Serialize)]
+/// struct Config {
+///     host: String,
+///     api_key: envy::Secret<String>,
+/// }
+///
+/// let vars = vec![
+///     (String::from("HOST"), String::from("localhost")),
+///     (String::from("API_KEY"), String::from("hunter2")),
+/// ];
+/// let config = envy::from_iter::<_, Config>(vars).unwrap();
+/// assert_eq!(config.api_key.expose(), "hunter2");
+/// assert_eq!(
+///     envy::to_vec_redacted(&config).unwrap(),
+///     vec![
+///         (String::from("HOST"), String::from("localhost")),
+///         (String::from("API_KEY"), String::from("***")),
+///     ]
+/// );
+/// ```
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    /// Wraps a secret value
+    pub fn new(value: T) -> Self {
+        Secret(value)
+    }
+
+    /// Returns the secret value
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    /// Unwraps the secret value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Secret<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Secret(value)
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Secret<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        T::deserialize(deserializer).map(Secret)
+    }
+}
+
+impl<T> Serialize for Secret<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if EXPOSED.with(Cell::get) {
+            return self.0.serialize(serializer);
+        }
+        serializer.serialize_str("***")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, Serialize, Debug)]
+    pub struct Credentials {
+        user: String,
+        password: Secret<String>,
+        token: Option<Secret<String>>,
+        #[serde(skip_serializing)]
+        pin: u16,
+    }
+
+    #[test]
+    fn redacts_secrets_in_dumps() {
+        let data = vec![
+            (String::from("USER"), String::from("admin")),
+            (String::from("PASSWORD"), String::from("hunter2")),
+            (String::from("PIN"), String::from("1234")),
+        ];
+        let credentials = crate::from_iter::<_, Credentials>(data).unwrap();
+        assert_eq!(credentials.password.expose(), "hunter2");
+        assert_eq!(credentials.pin, 1234);
+        assert_eq!(
+            format!("{:?}", credentials.password),
+            String::from("Secret(***)")
+        );
+        assert_eq!(
+            crate::to_vec_redacted(&credentials),
+            Ok(vec![
+                (String::from("USER"), String::from("admin")),
+                (String::from("PASSWORD"), String::from("***")),
+            ])
+        );
+    }
+}
@@ -0,0 +1,761 @@
+//! Serialization of types into env var pairs
+use crate::{Error, Result, NESTING_SEPARATOR};
+use serde::ser::{self, Serialize};
+
+/// Serializes a value into `(String, String)` pairs keyed by
+/// uppercased field names
+pub(crate) struct Serializer<'a> {
+    output: &'a mut Vec<(String, String)>,
+    key: String,
+}
+
+impl<'a> Serializer<'a> {
+    pub(crate) fn new(output: &'a mut Vec<(String, String)>) -> Self {
+        Serializer {
+            output,
+            key: String::new(),
+        }
+    }
+
+    fn push(
+        self,
+        value: String,
+    ) -> Result<()> {
+        if self.key.is_empty() {
+            return Err(ser::Error::custom(
+                "only structs and maps can be serialized into env vars",
+            ));
+        }
+        self.output.push((self.key, value));
+        Ok(())
+    }
+
+    fn child(
+        &mut self,
+        name: &str,
+    ) -> Serializer<'_> {
+        let name = name.to_uppercase();
+        let key = if self.key.is_empty() {
+            name
+        } else {
+            format!("{}{}{}", self.key, NESTING_SEPARATOR, name)
+        };
+        Serializer {
+            output: self.output,
+            key,
+        }
+    }
+}
+
+macro_rules! serialize_display {
+    ($($ty:ty => $method:ident,)*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Self::Ok> {
+                self.push(v.to_string())
+            }
+        )*
+    }
+}
+
+impl<'a> ser::Serializer for Serializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Joined<'a>;
+    type SerializeTuple = Joined<'a>;
+    type SerializeTupleStruct = Joined<'a>;
+    type SerializeTupleVariant = ser::Impossible<(), Error>;
+    type SerializeMap = Fields<'a>;
+    type SerializeStruct = Fields<'a>;
+    type SerializeStructVariant = ser::Impossible<(), Error>;
+
+    serialize_display! {
+        bool => serialize_bool,
+        i8 => serialize_i8,
+        i16 => serialize_i16,
+        i32 => serialize_i32,
+        i64 => serialize_i64,
+        i128 => serialize_i128,
+        u8 => serialize_u8,
+        u16 => serialize_u16,
+        u32 => serialize_u32,
+        u64 => serialize_u64,
+        u128 => serialize_u128,
+        f32 => serialize_f32,
+        f64 => serialize_f64,
+        char => serialize_char,
+        &str => serialize_str,
+    }
+
+    fn serialize_bytes(
+        self,
+        v: &[u8],
+    ) -> Result<()> {
+        self.push(ValueSerializer.serialize_bytes(v)?)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(
+        self,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.push(String::new())
+    }
+
+    fn serialize_unit_struct(
+        self,
+        _name: &'static str,
+    ) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.push(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _value: &T,
+    ) -> Result<()> {
+        Err(unsupported(variant))
+    }
+
+    fn serialize_seq(
+        self,
+        _len: Option<usize>,
+    ) -> Result<Joined<'a>> {
+        Ok(Joined::new(self, ","))
+    }
+
+    fn serialize_tuple(
+        self,
+        _len: usize,
+    ) -> Result<Joined<'a>> {
+        Ok(Joined::new(self, ":"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Joined<'a>> {
+        Ok(Joined::new(self, ":"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(unsupported(variant))
+    }
+
+    fn serialize_map(
+        self,
+        _len: Option<usize>,
+    ) -> Result<Fields<'a>> {
+        // maps within a field are written to a single var the way they are read
+        let entries = if self.key.is_empty() {
+            None
+        } else {
+            Some(Vec::new())
+        };
+        Ok(Fields {
+            serializer: self,
+            key: None,
+            entries,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Fields<'a>> {
+        Ok(Fields {
+            serializer: self,
+            key: None,
+            entries: None,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(unsupported(variant))
+    }
+}
+
+fn unsupported(variant: &str) -> Error {
+    ser::Error::custom(format_args!(
+        "enum variant {} can not be serialized into an env var as it holds data",
+        variant
+    ))
+}
+
+/// Escapes backslashes and separators within a sequence element, the way sequences are
+/// split when deserialized
+fn escape(
+    value: &str,
+    separator: &str,
+) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(separator, &format!("\\{}", separator))
+}
+
+/// Serializes the elements of a sequence or tuple, joining them with a separator
+pub(crate) struct Joined<'a> {
+    serializer: Serializer<'a>,
+    separator: &'static str,
+    values: Vec<String>,
+}
+
+impl<'a> Joined<'a> {
+    fn new(
+        serializer: Serializer<'a>,
+        separator: &'static str,
+    ) -> Self {
+        Joined {
+            serializer,
+            separator,
+            values: Vec::new(),
+        }
+    }
+
+    fn push<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        let value = self.values.join(self.separator);
+        self.serializer.push(value)
+    }
+}
+
+impl ser::SerializeSeq for Joined<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        let value = value.serialize(ValueSerializer)?;
+        self.values.push(escape(&value, self.separator));
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for Joined<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for Joined<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+/// Serializes the fields of a struct or the entries of a map as separate env vars
+pub(crate) struct Fields<'a> {
+    serializer: Serializer<'a>,
+    key: Option<String>,
+    /// The `key:value` entries of a map written to a single var, like `a:1,b:2`
+    entries: Option<Vec<String>>,
+}
+
+impl ser::SerializeStruct for Fields<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self.serializer.child(key))
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for Fields<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(
+        &mut self,
+        key: &T,
+    ) -> Result<()> {
+        self.key = Some(key.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        let key = self.key.take().unwrap_or_default();
+        let entries = match &mut self.entries {
+            Some(entries) => entries,
+            None => return value.serialize(self.serializer.child(&key)),
+        };
+        if key.contains(':') {
+            return Err(ser::Error::custom(format_args!(
+                "map key '{}' can not be serialized into an env var as it contains ':'",
+                key
+            )));
+        }
+        let value = value.serialize(ValueSerializer)?;
+        entries.push(escape(&format!("{}:{}", key, value), ","));
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        match self.entries {
+            // sorted so maps without a defined order, like `HashMap`, serialize consistently
+            Some(mut entries) => {
+                entries.sort();
+                self.serializer.push(entries.join(","))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Serializes a single value into a string
+struct ValueSerializer;
+
+macro_rules! serialize_value_display {
+    ($($ty:ty => $method:ident,)*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<String> {
+                Ok(v.to_string())
+            }
+        )*
+    }
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = JoinedValue;
+    type SerializeTuple = JoinedValue;
+    type SerializeTupleStruct = JoinedValue;
+    type SerializeTupleVariant = ser::Impossible<String, Error>;
+    type SerializeMap = ser::Impossible<String, Error>;
+    type SerializeStruct = ser::Impossible<String, Error>;
+    type SerializeStructVariant = ser::Impossible<String, Error>;
+
+    serialize_value_display! {
+        bool => serialize_bool,
+        i8 => serialize_i8,
+        i16 => serialize_i16,
+        i32 => serialize_i32,
+        i64 => serialize_i64,
+        i128 => serialize_i128,
+        u8 => serialize_u8,
+        u16 => serialize_u16,
+        u32 => serialize_u32,
+        u64 => serialize_u64,
+        u128 => serialize_u128,
+        f32 => serialize_f32,
+        f64 => serialize_f64,
+        char => serialize_char,
+        &str => serialize_str,
+    }
+
+    fn serialize_bytes(
+        self,
+        v: &[u8],
+    ) -> Result<String> {
+        Ok(v.iter()
+            .map(|b| b.to_string())
+            .collect::<Vec<_>>()
+            .join(","))
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        Ok(String::new())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(
+        self,
+        value: &T,
+    ) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        Ok(String::new())
+    }
+
+    fn serialize_unit_struct(
+        self,
+        _name: &'static str,
+    ) -> Result<String> {
+        Ok(String::new())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _value: &T,
+    ) -> Result<String> {
+        Err(unsupported(variant))
+    }
+
+    fn serialize_seq(
+        self,
+        _len: Option<usize>,
+    ) -> Result<JoinedValue> {
+        Ok(JoinedValue::new(","))
+    }
+
+    fn serialize_tuple(
+        self,
+        _len: usize,
+    ) -> Result<JoinedValue> {
+        Ok(JoinedValue::new(":"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<JoinedValue> {
+        Ok(JoinedValue::new(":"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(unsupported(variant))
+    }
+
+    fn serialize_map(
+        self,
+        _len: Option<usize>,
+    ) -> Result<Self::SerializeMap> {
+        Err(ser::Error::custom(
+            "maps can not be serialized within a single env var",
+        ))
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct> {
+        Err(ser::Error::custom(format_args!(
+            "struct {} can not be serialized within a single env var",
+            name
+        )))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(unsupported(variant))
+    }
+}
+
+/// Serializes the elements of a sequence or tuple nested within a single value
+struct JoinedValue {
+    separator: &'static str,
+    values: Vec<String>,
+}
+
+impl JoinedValue {
+    fn new(separator: &'static str) -> Self {
+        JoinedValue {
+            separator,
+            values: Vec::new(),
+        }
+    }
+}
+
+impl ser::SerializeSeq for JoinedValue {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        let value = value.serialize(ValueSerializer)?;
+        self.values.push(escape(&value, self.separator));
+        Ok(())
+    }
+
+    fn end(self) -> Result<String> {
+        Ok(self.values.join(self.separator))
+    }
+}
+
+impl ser::SerializeTuple for JoinedValue {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<String> {
+        Ok(self.values.join(self.separator))
+    }
+}
+
+impl ser::SerializeTupleStruct for JoinedValue {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<String> {
+        Ok(self.values.join(self.separator))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_iter, to_vec, Error};
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Database {
+        host: String,
+        replicas: Vec<(String, u16)>,
+    }
+
+    #[derive(Serialize)]
+    struct Config {
+        debug: bool,
+        database: Database,
+        timeout: Option<u32>,
+    }
+
+    #[test]
+    fn serializes_nested_structs() {
+        let config = Config {
+            debug: true,
+            database: Database {
+                host: String::from("localhost"),
+                replicas: vec![(String::from("a"), 1), (String::from("b"), 2)],
+            },
+            timeout: None,
+        };
+        assert_eq!(
+            to_vec(&config),
+            Ok(vec![
+                (String::from("DEBUG"), String::from("true")),
+                (String::from("DATABASE__HOST"), String::from("localhost")),
+                (String::from("DATABASE__REPLICAS"), String::from("a:1,b:2")),
+            ])
+        );
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Service {
+        name: String,
+        tags: Vec<String>,
+        port: Option<u16>,
+        #[serde(skip_serializing, default)]
+        token: String,
+    }
+
+    #[test]
+    fn round_trips_through_from_iter() {
+        let service = Service {
+            name: String::from("api"),
+            tags: vec![String::from("a,b"), String::from("c:\\d")],
+            port: None,
+            token: String::from("secret"),
+        };
+        let pairs = to_vec(&service).unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                (String::from("NAME"), String::from("api")),
+                (String::from("TAGS"), String::from("a\\,b,c:\\\\d")),
+            ]
+        );
+        assert_eq!(
+            from_iter::<_, Service>(pairs),
+            Ok(Service {
+                token: String::new(),
+                ..service
+            })
+        );
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Upstream {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Gateway {
+        labels: BTreeMap<String, String>,
+        upstream: Upstream,
+    }
+
+    #[test]
+    fn round_trips_maps_and_nested_structs() {
+        let gateway = Gateway {
+            labels: BTreeMap::from([
+                (String::from("team"), String::from("a,b")),
+                (String::from("url"), String::from("http://x")),
+            ]),
+            upstream: Upstream {
+                host: String::from("localhost"),
+                port: 8080,
+            },
+        };
+        let pairs = to_vec(&gateway).unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                (
+                    String::from("LABELS"),
+                    String::from("team:a\\,b,url:http://x")
+                ),
+                (String::from("UPSTREAM__HOST"), String::from("localhost")),
+                (String::from("UPSTREAM__PORT"), String::from("8080")),
+            ]
+        );
+        assert_eq!(
+            crate::Config::new()
+                .nested(true)
+                .from_iter::<_, Gateway>(pairs),
+            Ok(gateway)
+        );
+    }
+
+    #[test]
+    fn fails_to_serialize_map_keys_containing_colons() {
+        let labels = BTreeMap::from([(String::from("a:b"), String::from("c"))]);
+        assert_eq!(
+            to_vec(&Gateway {
+                labels,
+                upstream: Upstream {
+                    host: String::from("localhost"),
+                    port: 8080,
+                },
+            }),
+            Err(Error::Custom(String::from(
+                "map key 'a:b' can not be serialized into an env var as it contains ':'"
+            )))
+        );
+    }
+
+    #[test]
+    fn fails_to_serialize_scalars() {
+        assert_eq!(
+            to_vec(&1),
+            Err(Error::Custom(String::from(
+                "only structs and maps can be serialized into env vars"
+            )))
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Integration with the [config](https://docs.rs/config) crate
+use crate::Config;
+use std::{env, fmt};
+
+/// A `config::Source` reading env vars the way a [Config](struct.Config.html) does
+///
+/// Prefixes are stripped, names are normalized and, in nested mode, separated names
+/// become nested tables, so the values layer into a `config::Config` like any other source.
+///
+/// These types are created with [Config::source](struct.Config.html#method.source).
+#[derive(Clone)]
+pub struct ConfigSource {
+    config: Config,
+    vars: Option<Vec<(String, String)>>,
+}
+
+impl fmt::Debug for ConfigSource {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.debug_struct("ConfigSource")
+            .field("prefix", &self.config.prefix)
+            .finish()
+    }
+}
+
+impl config::Source for ConfigSource {
+    fn clone_into_box(&self) -> Box<dyn config::Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> Result<config::Map<String, config::Value>, config::ConfigError> {
+        let vars = match &self.vars {
+            Some(vars) => vars.clone(),
+            None => env::vars().collect(),
+        };
+        let options = &self.config.options;
+        let origin = String::from("the environment");
+        Ok(vars
+            .into_iter()
+            .filter(|(_, v)| !(options.empty_as_absent && v.is_empty()))
+            .filter_map(|(k, v)| {
+                let k = self.config.decode_key(k);
+                let name = options.normalize(self.config.strip_affixes(&k)?);
+                let key = if options.nested {
+                    name.split(options.nesting_separator())
+                        .collect::<Vec<_>>()
+                        .join(".")
+                } else {
+                    name
+                };
+                Some((
+                    key,
+                    config::Value::new(Some(&origin), options.alias(v.into()).into_owned()),
+                ))
+            })
+            .collect())
+    }
+}
+
+impl Config {
+    /// Creates a `config::Source` reading env variables
+    pub fn source(&self) -> ConfigSource {
+        ConfigSource {
+            config: self.clone(),
+            vars: None,
+        }
+    }
+
+    /// Creates a `config::Source` reading (String, String) tuples
+    pub fn source_from_iter<Iter>(
+        &self,
+        iter: Iter,
+    ) -> ConfigSource
+    where
+        Iter: IntoIterator<Item = (String, String)>,
+    {
+        ConfigSource {
+            config: self.clone(),
+            vars: Some(iter.into_iter().collect()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Database {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct App {
+        name: String,
+        db: Database,
+    }
+
+    #[test]
+    fn builds_config_from_source() {
+        let data = vec![
+            (String::from("APP_NAME"), String::from("demo")),
+            (String::from("APP_DB__HOST"), String::from("localhost")),
+            (String::from("APP_DB__PORT"), String::from("5432")),
+            (String::from("OTHER"), String::from("ignored")),
+        ];
+        let source = Config::new()
+            .prefix("APP_")
+            .nested(true)
+            .source_from_iter(data);
+        let app = config::Config::builder()
+            .set_default("name", "default")
+            .unwrap()
+            .add_source(source)
+            .build()
+            .unwrap()
+            .try_deserialize::<App>()
+            .unwrap();
+        assert_eq!(
+            app,
+            App {
+                name: String::from("demo"),
+                db: Database {
+                    host: String::from("localhost"),
+                    port: 5432
+                }
+            }
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! A dynamically typed env var value
+use serde::de::{self, Deserialize, Deserializer};
+use std::fmt;
+
+/// A value coerced to its most specific type
+///
+/// This suits tools inspecting arbitrary env vars, i.e. with
+/// `envy::from_env::<BTreeMap<String, envy::Value>>()`. Values are coerced by the first
+/// matching rule:
+///
+/// 1. `true` and `false` become a `Bool`
+/// 2. values parsing as an `i64`, like `8080` or `-1`, become an `Int`
+/// 3. values parsing as an `f64` which contain a digit, like `0.5` or `1e3`, become a `Float`
+/// 4. values containing a comma become a `List` of their trimmed elements, each coerced by
+///    these same rules
+/// 5. anything else, including empty values, becomes a `Str`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// A `true` or `false` value
+    Bool(bool),
+    /// A whole number within the range of an `i64`
+    Int(i64),
+    /// A number with a fractional part or exponent, or too large for an `i64`
+    Float(f64),
+    /// Any value not coerced to another variant, including empty values
+    Str(String),
+    /// A comma separated value, with each element coerced on its own
+    List(Vec<Value>),
+}
+
+impl Value {
+    /// Coerces a raw value to its most specific type
+    pub fn coerce(value: &str) -> Value {
+        if let Ok(value) = value.parse::<bool>() {
+            return Value::Bool(value);
+        }
+        if let Ok(value) = value.parse::<i64>() {
+            return Value::Int(value);
+        }
+        if value.contains(|c: char| c.is_ascii_digit()) {
+            if let Ok(value) = value.parse::<f64>() {
+                return Value::Float(value);
+            }
+        }
+        if value.contains(',') {
+            return Value::List(value.split(',').map(|v| Value::coerce(v.trim())).collect());
+        }
+        Value::Str(value.to_owned())
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> de::Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(
+        &self,
+        formatter: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        formatter.write_str("any value")
+    }
+
+    fn visit_bool<E>(
+        self,
+        value: bool,
+    ) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E>(
+        self,
+        value: i64,
+    ) -> Result<Value, E> {
+        Ok(Value::Int(value))
+    }
+
+    fn visit_u64<E>(
+        self,
+        value: u64,
+    ) -> Result<Value, E> {
+        Ok(i64::try_from(value).map_or(Value::Float(value as f64), Value::Int))
+    }
+
+    fn visit_f64<E>(
+        self,
+        value: f64,
+    ) -> Result<Value, E> {
+        Ok(Value::Float(value))
+    }
+
+    fn visit_str<E>(
+        self,
+        value: &str,
+    ) -> Result<Value, E> {
+        Ok(Value::coerce(value))
+    }
+
+    fn visit_seq<A>(
+        self,
+        mut seq: A,
+    ) -> Result<Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::List(values))
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn coerces_values() {
+        let data = vec![
+            (String::from("PORT"), String::from("8080")),
+            (String::from("DEBUG"), String::from("true")),
+            (String::from("RATIO"), String::from("0.5")),
+            (String::from("HOSTS"), String::from("a, 2")),
+            (String::from("NAME"), String::from("inf")),
+        ];
+        let values = crate::from_iter::<_, BTreeMap<String, Value>>(data).unwrap();
+        assert_eq!(values["port"], Value::Int(8080));
+        assert_eq!(values["debug"], Value::Bool(true));
+        assert_eq!(values["ratio"], Value::Float(0.5));
+        assert_eq!(
+            values["hosts"],
+            Value::List(vec![Value::Str(String::from("a")), Value::Int(2)])
+        );
+        assert_eq!(values["name"], Value::Str(String::from("inf")));
+    }
+}